@@ -1,5 +1,11 @@
-use starknet::core::types::FromStrError;
-use std::{fmt::Debug, str::FromStr};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{FieldElement, FromStrError};
+use std::{
+    cmp::Ordering,
+    fmt::{Debug, Display, Formatter},
+    str::FromStr,
+};
 use strum_macros::EnumIter;
 use thiserror::Error;
 
@@ -8,6 +14,10 @@ use thiserror::Error;
 pub enum HasherError {
     #[error("Invalid hashing function")]
     InvalidHashingFunction,
+    #[error("Invalid node hashing")]
+    InvalidNodeHashing,
+    #[error("Invalid domain separation")]
+    InvalidDomainSeparation,
     #[error(
         "Element size {element_size} is too big for hashing function with block size {block_size_bits}"
     )]
@@ -21,6 +31,8 @@ pub enum HasherError {
     HexDecodeError(#[from] hex::FromHexError),
     #[error("Fail to convert to felt")]
     FeltConversionError(#[from] FromStrError),
+    #[error("Invalid hex hash {0}")]
+    InvalidHash(String),
 }
 
 /// A trait for hash functions
@@ -42,6 +54,27 @@ pub trait Hasher: Send + Sync + Debug {
 
     /// Returns the block size in bits
     fn get_block_size_bits(&self) -> usize;
+
+    /// Hashes raw bytes, inputs that do not fit in a single element are rejected by default
+    fn hash_data(&self, data: &[u8]) -> Result<String, HasherError> {
+        let element_size = data.len() * 8;
+        if element_size > self.get_block_size_bits() {
+            return Err(HasherError::InvalidElementSize {
+                element_size,
+                block_size_bits: self.get_block_size_bits(),
+            });
+        }
+
+        self.hash_single(&format!("0x{}", hex::encode(data)))
+    }
+
+    /// Returns the (leaf, node) tags used when [`DomainSeparation::Tagged`] is enabled
+    ///
+    /// The tags only have to differ from each other, hashes of different hashers never meet, so
+    /// the built-in hashers share `0x0` and `0x1`, which fit every one of their input encodings.
+    fn get_domain_tags(&self) -> (String, String) {
+        ("0x0".to_string(), "0x1".to_string())
+    }
 }
 
 /// Hashing functions types supported by the hasher
@@ -65,18 +98,182 @@ impl FromStr for HashingFunction {
     }
 }
 
-impl ToString for HashingFunction {
-    fn to_string(&self) -> String {
+impl Display for HashingFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashingFunction::Keccak256 => write!(f, "keccak"),
+            HashingFunction::Poseidon => write!(f, "poseidon"),
+            HashingFunction::Pedersen => write!(f, "pedersen"),
+        }
+    }
+}
+
+/// How two sibling nodes are combined into their parent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NodeHashing {
+    /// Left node first, as given by the node positions
+    #[default]
+    Positional,
+    /// Smallest node first, so verifiers do not need the node positions
+    Sorted,
+}
+
+impl NodeHashing {
+    /// Hashes the parent of `left` and `right`
+    pub fn hash_pair<H: Hasher + ?Sized>(
+        &self,
+        hasher: &H,
+        left: String,
+        right: String,
+    ) -> Result<String, HasherError> {
+        let (first, second) = self.order(left, right)?;
+        hasher.hash(vec![first, second])
+    }
+
+    /// Orders two sibling nodes the way they are hashed
+    pub fn order(&self, left: String, right: String) -> Result<(String, String), HasherError> {
         match self {
-            HashingFunction::Keccak256 => "keccak".to_string(),
-            HashingFunction::Poseidon => "poseidon".to_string(),
-            HashingFunction::Pedersen => "pedersen".to_string(),
+            NodeHashing::Sorted if compare_hashes(&left, &right)? == Ordering::Greater => {
+                Ok((right, left))
+            }
+            _ => Ok((left, right)),
         }
     }
 }
 
+/// Whether leaves and internal nodes are hashed in distinct domains
+///
+/// When tagged, a leaf is stored as `hash([leaf_tag, value])` and a node as
+/// `hash([node_tag, left, right])`, so a leaf can never be passed off as a node.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DomainSeparation {
+    /// Leaves are stored as given and nodes are `hash([left, right])`
+    #[default]
+    Disabled,
+    /// Leaves and nodes are prefixed with the tags of [`Hasher::get_domain_tags`]
+    Tagged,
+}
+
+impl DomainSeparation {
+    /// Hashes a leaf value into the hash stored in the tree
+    pub fn hash_leaf<H: Hasher + ?Sized>(
+        &self,
+        hasher: &H,
+        value: String,
+    ) -> Result<String, HasherError> {
+        match self {
+            DomainSeparation::Disabled => Ok(value),
+            DomainSeparation::Tagged => hasher.hash(vec![hasher.get_domain_tags().0, value]),
+        }
+    }
+
+    /// Hashes the parent of `left` and `right`, ordered by `node_hashing`
+    pub fn hash_node<H: Hasher + ?Sized>(
+        &self,
+        hasher: &H,
+        node_hashing: NodeHashing,
+        left: String,
+        right: String,
+    ) -> Result<String, HasherError> {
+        let (first, second) = node_hashing.order(left, right)?;
+        match self {
+            DomainSeparation::Disabled => hasher.hash(vec![first, second]),
+            DomainSeparation::Tagged => {
+                hasher.hash(vec![hasher.get_domain_tags().1, first, second])
+            }
+        }
+    }
+}
+
+impl FromStr for DomainSeparation {
+    type Err = HasherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disabled" => Ok(DomainSeparation::Disabled),
+            "tagged" => Ok(DomainSeparation::Tagged),
+            _ => Err(HasherError::InvalidDomainSeparation),
+        }
+    }
+}
+
+impl Display for DomainSeparation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DomainSeparation::Disabled => write!(f, "disabled"),
+            DomainSeparation::Tagged => write!(f, "tagged"),
+        }
+    }
+}
+
+impl FromStr for NodeHashing {
+    type Err = HasherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "positional" => Ok(NodeHashing::Positional),
+            "sorted" => Ok(NodeHashing::Sorted),
+            _ => Err(HasherError::InvalidNodeHashing),
+        }
+    }
+}
+
+impl Display for NodeHashing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeHashing::Positional => write!(f, "positional"),
+            NodeHashing::Sorted => write!(f, "sorted"),
+        }
+    }
+}
+
+/// Compares two hashes by value, regardless of their case and leading zeros
+///
+/// Like the hashers, a `0x` prefixed value is hex and any other value is decimal. Fails with
+/// [`HasherError::InvalidHash`] if either is not a number.
+pub fn compare_hashes(a: &str, b: &str) -> Result<Ordering, HasherError> {
+    let parse = |hash: &str| {
+        let (digits, radix) = match hash.strip_prefix("0x").or_else(|| hash.strip_prefix("0X")) {
+            Some(digits) => (digits, 16),
+            None => (hash, 10),
+        };
+        //? A bare `0x` is zero, like the empty bytes some encoders return
+        if digits.is_empty() && radix == 16 {
+            return Ok(BigUint::default());
+        }
+        if digits.is_empty() || !digits.chars().all(|digit| digit.is_digit(radix)) {
+            return Err(HasherError::InvalidHash(hash.to_string()));
+        }
+        BigUint::parse_bytes(digits.as_bytes(), radix)
+            .ok_or_else(|| HasherError::InvalidHash(hash.to_string()))
+    };
+    Ok(parse(a)?.cmp(&parse(b)?))
+}
+
 /// Returns the byte size of a hex string
 pub fn byte_size(hex: &str) -> usize {
     let hex = hex.strip_prefix("0x").unwrap_or(hex);
     hex.len() / 2
 }
+
+/// Bytes packed in each felt by [`bytes_to_felts`], the same as Cairo's `bytes31`
+pub const FELT_CHUNK_BYTES: usize = 31;
+
+/// Serializes raw bytes like Cairo serializes a `ByteArray`
+///
+/// That is the number of full [`FELT_CHUNK_BYTES`] words, the full words big-endian, then the
+/// remaining bytes as a big-endian pending word and their count.
+pub fn bytes_to_felts(data: &[u8]) -> Vec<FieldElement> {
+    let full_words = data.len() / FELT_CHUNK_BYTES;
+    let (words, pending_word) = data.split_at(full_words * FELT_CHUNK_BYTES);
+
+    let mut felts = Vec::with_capacity(full_words + 3);
+    felts.push(FieldElement::from(full_words));
+    //? A 31 byte word is always below the field prime
+    for word in words.chunks(FELT_CHUNK_BYTES) {
+        felts.push(FieldElement::from_byte_slice_be(word).unwrap());
+    }
+    felts.push(FieldElement::from_byte_slice_be(pending_word).unwrap());
+    felts.push(FieldElement::from(pending_word.len()));
+    felts
+}
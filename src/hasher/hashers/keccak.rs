@@ -76,6 +76,15 @@ impl Hasher for KeccakHasher {
         self.hash(vec![data.to_string()])
     }
 
+    /// Hashes bytes of any length, e.g. an RLP encoded block header
+    fn hash_data(&self, data: &[u8]) -> Result<String, HasherError> {
+        let mut output = [0u8; 32];
+        let mut keccak = Keccak::v256();
+        keccak.update(data);
+        keccak.finalize(&mut output);
+        Ok(format!("0x{}", hex::encode(output)))
+    }
+
     fn get_genesis(&self) -> Result<String, HasherError> {
         let genesis_str = "brave new world";
         let hex = format!("0x{}", hex::encode(genesis_str));
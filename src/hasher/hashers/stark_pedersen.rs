@@ -1,6 +1,9 @@
-use starknet::core::{crypto::pedersen_hash, types::FieldElement};
+use starknet::core::{
+    crypto::{compute_hash_on_elements, pedersen_hash},
+    types::FieldElement,
+};
 
-use crate::hasher::{byte_size, HasherError, HashingFunction};
+use crate::hasher::{byte_size, bytes_to_felts, HasherError, HashingFunction};
 
 use super::super::Hasher;
 
@@ -54,6 +57,11 @@ impl Hasher for StarkPedersenHasher {
     fn get_block_size_bits(&self) -> usize {
         self.block_size_bits
     }
+
+    /// Hashes bytes of any length with Cairo's `hash_state` chain over their `ByteArray` serialization, see [`bytes_to_felts`]
+    fn hash_data(&self, data: &[u8]) -> Result<String, HasherError> {
+        Ok(format_hash(compute_hash_on_elements(&bytes_to_felts(data))))
+    }
 }
 
 impl StarkPedersenHasher {
@@ -72,11 +80,9 @@ impl StarkPedersenHasher {
         let result = pedersen_hash(
             &FieldElement::from_hex_be(&data[0])?,
             &FieldElement::from_hex_be(&data[1])?,
-        )
-        .to_bytes_be();
+        );
 
-        let padded_hex_str = format!("0x{:0>64}", hex::encode(result));
-        Ok(padded_hex_str)
+        Ok(format_hash(result))
     }
 
     pub fn new() -> Self {
@@ -86,6 +92,11 @@ impl StarkPedersenHasher {
     }
 }
 
+/// Formats a hash as `0x` prefixed hex, padded to 64 digits
+fn format_hash(hash: FieldElement) -> String {
+    format!("0x{}", hex::encode(hash.to_bytes_be()))
+}
+
 impl Default for StarkPedersenHasher {
     fn default() -> Self {
         Self::new()
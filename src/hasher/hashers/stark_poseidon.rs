@@ -1,4 +1,4 @@
-use crate::hasher::{byte_size, HasherError, HashingFunction};
+use crate::hasher::{byte_size, bytes_to_felts, HasherError, HashingFunction};
 
 use super::super::Hasher;
 use starknet::core::types::FieldElement;
@@ -36,12 +36,7 @@ impl Hasher for StarkPoseidonHasher {
             _ => poseidon_hash_many(&field_elements),
         };
 
-        let mut hash = format!("{:x}", hash_core);
-        if self.should_pad {
-            hash = format!("{:0>63}", hash);
-        }
-        let hash = format!("0x{}", hash);
-        Ok(hash)
+        Ok(self.format_hash(hash_core))
     }
 
     fn is_element_size_valid(&self, element: &str) -> Result<bool, HasherError> {
@@ -69,6 +64,11 @@ impl Hasher for StarkPoseidonHasher {
     fn get_block_size_bits(&self) -> usize {
         self.block_size_bits
     }
+
+    /// Hashes bytes of any length as `poseidon_hash_span` of their Cairo `ByteArray` serialization, see [`bytes_to_felts`]
+    fn hash_data(&self, data: &[u8]) -> Result<String, HasherError> {
+        Ok(self.format_hash(poseidon_hash_many(&bytes_to_felts(data))))
+    }
 }
 
 impl StarkPoseidonHasher {
@@ -78,6 +78,16 @@ impl StarkPoseidonHasher {
             should_pad: should_pad.unwrap_or(false),
         }
     }
+
+    /// Formats a hash as `0x` prefixed hex, padded to 63 digits if `should_pad` is set
+    fn format_hash(&self, hash: FieldElement) -> String {
+        let hash = format!("{:x}", hash);
+        if self.should_pad {
+            format!("0x{:0>63}", hash)
+        } else {
+            format!("0x{}", hash)
+        }
+    }
 }
 
 impl Default for StarkPoseidonHasher {
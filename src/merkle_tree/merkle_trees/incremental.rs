@@ -1,17 +1,30 @@
+use futures::TryStreamExt;
 use indexmap::IndexMap;
-use std::{collections::HashMap, sync::Arc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    sync::Arc,
+};
 use thiserror::Error;
 
 use uuid::Uuid;
 
 use crate::{
-    hasher::{Hasher, HasherError},
-    store::{InStoreTable, InStoreTableError, Store, SubKey},
+    hasher::{DomainSeparation, Hasher, HasherError, NodeHashing},
+    store::{InStoreTable, InStoreTableError, Store, StoreError, SubKey},
 };
 
 #[derive(Debug)]
 pub enum TreeMetadataKeys {
     RootHash,
+    NodeHashing,
+    DomainSeparation,
+    Lazy,
+    Size,
+    Depth,
+    NullValue,
+    Hasher,
 }
 
 #[derive(Debug)]
@@ -30,12 +43,82 @@ pub enum IncrementalMerkleTreeError {
     RootHashNotFound(String),
     #[error("Invalid index")]
     InvalidIndex,
+    #[error("A tree can only grow")]
+    InvalidSize,
+    #[error("Tree not found for mmr_id: {0}")]
+    TreeNotFound(String),
+    #[error("Invalid tree metadata: {0}")]
+    InvalidMetadata(String),
+    #[error("Leaf value does not match the tree")]
+    InvalidLeafValue,
     #[error("Wanted value not found")]
     WantedValueNotFound,
     #[error("Hasher error: {0}")]
     HasherError(#[from] HasherError),
     #[error("Store table error: {0}")]
     InStoreTableError(#[from] InStoreTableError),
+    #[error("Store error: {0}")]
+    StoreError(#[from] StoreError),
+}
+
+/// How an [`IncrementalMerkleTree`] hashes and stores its nodes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IncrementalMerkleTreeOptions {
+    pub node_hashing: NodeHashing,
+    pub domain_separation: DomainSeparation,
+    /// Only store the nodes that differ from the empty tree, instead of writing every node
+    pub lazy: bool,
+}
+
+/// A proof that a value is a leaf of an [`IncrementalMerkleTree`]
+///
+/// It can be verified with [`IncrementalMerkleProof::verify`] without access to the tree. The
+/// root and hashing settings it carries are informative, the verifier supplies the trusted ones.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IncrementalMerkleProof {
+    pub index: usize,
+    pub value: String,
+    /// The siblings from the leaf up to the root
+    pub siblings: Vec<String>,
+    pub tree_depth: usize,
+    pub root: String,
+    #[serde(default)]
+    pub node_hashing: NodeHashing,
+    #[serde(default)]
+    pub domain_separation: DomainSeparation,
+}
+
+impl IncrementalMerkleProof {
+    /// Checks that the proof leads to the trusted `root_hash`, hashing nodes as `options` says
+    ///
+    /// A proof claiming other hashing settings than `options` is rejected.
+    pub fn verify<H: Hasher + ?Sized>(
+        &self,
+        hasher: &H,
+        root_hash: &str,
+        options: &IncrementalMerkleTreeOptions,
+    ) -> Result<bool, IncrementalMerkleTreeError> {
+        if self.node_hashing != options.node_hashing
+            || self.domain_separation != options.domain_separation
+            || self.siblings.len() != self.tree_depth
+            || self.index
+                >= 1usize
+                    .checked_shl(self.tree_depth as u32)
+                    .unwrap_or(usize::MAX)
+        {
+            return Ok(false);
+        }
+
+        let root = compute_root(
+            hasher,
+            options.node_hashing,
+            options.domain_separation,
+            self.index,
+            &self.value,
+            &self.siblings,
+        )?;
+        Ok(root == root_hash)
+    }
 }
 
 pub struct IncrementalMerkleTree<H> {
@@ -46,6 +129,11 @@ pub struct IncrementalMerkleTree<H> {
     pub hasher: H,
     pub size: usize,
     pub null_value: String,
+    pub node_hashing: NodeHashing,
+    pub domain_separation: DomainSeparation,
+    pub lazy: bool,
+    //? Per depth, the hash of an empty node and of the last node of the level when empty
+    default_nodes: Vec<(String, String)>,
 }
 
 impl<H> IncrementalMerkleTree<H>
@@ -58,6 +146,7 @@ where
         hasher: H,
         store: Arc<dyn Store>,
         mmr_id: Option<String>,
+        options: IncrementalMerkleTreeOptions,
     ) -> Self {
         let mmr_id = mmr_id.unwrap_or_else(|| Uuid::new_v4().to_string());
 
@@ -75,6 +164,10 @@ where
             hasher,
             size,
             null_value,
+            node_hashing: options.node_hashing,
+            domain_separation: options.domain_separation,
+            lazy: options.lazy,
+            default_nodes: Vec::new(),
         }
     }
 
@@ -85,26 +178,219 @@ where
         store: Arc<dyn Store>,
         mmr_id: Option<String>,
     ) -> Result<Self, IncrementalMerkleTreeError> {
-        let tree = IncrementalMerkleTree::new(size, null_value, hasher, store, mmr_id);
-        let nodes = tree.render_empty_tree()?;
-        let nodes_hashmap: HashMap<SubKey, String> =
-            nodes
-                .iter()
-                .flatten()
-                .fold(HashMap::new(), |mut acc, curr| {
-                    let key = SubKey::String(format!("{}:{}", curr.depth, curr.index));
-                    acc.insert(key, curr.hash.clone());
-                    acc
-                });
+        IncrementalMerkleTree::initialize_with_node_hashing(
+            size,
+            null_value,
+            hasher,
+            store,
+            mmr_id,
+            NodeHashing::default(),
+        )
+        .await
+    }
 
-        tree.nodes.set_many(nodes_hashmap).await?;
+    /// Same as [`IncrementalMerkleTree::initialize`], combining sibling nodes with `node_hashing`
+    pub async fn initialize_with_node_hashing(
+        size: usize,
+        null_value: String,
+        hasher: H,
+        store: Arc<dyn Store>,
+        mmr_id: Option<String>,
+        node_hashing: NodeHashing,
+    ) -> Result<Self, IncrementalMerkleTreeError> {
+        IncrementalMerkleTree::initialize_with_hashing(
+            size,
+            null_value,
+            hasher,
+            store,
+            mmr_id,
+            node_hashing,
+            DomainSeparation::default(),
+        )
+        .await
+    }
+
+    /// Same as [`IncrementalMerkleTree::initialize_with_node_hashing`], hashing leaves and
+    /// nodes in distinct domains when `domain_separation` is tagged
+    ///
+    /// Both settings are recorded in the tree metadata.
+    pub async fn initialize_with_hashing(
+        size: usize,
+        null_value: String,
+        hasher: H,
+        store: Arc<dyn Store>,
+        mmr_id: Option<String>,
+        node_hashing: NodeHashing,
+        domain_separation: DomainSeparation,
+    ) -> Result<Self, IncrementalMerkleTreeError> {
+        IncrementalMerkleTree::initialize_with_options(
+            size,
+            null_value,
+            hasher,
+            store,
+            mmr_id,
+            IncrementalMerkleTreeOptions {
+                node_hashing,
+                domain_separation,
+                lazy: false,
+            },
+        )
+        .await
+    }
+
+    /// Same as [`IncrementalMerkleTree::initialize_with_hashing`], with every setting in `options`
+    ///
+    /// A lazy tree only keeps one default hash per level in memory and stores the nodes that
+    /// differ from them, so its storage grows with the leaves that were set, not with `size`.
+    /// Its root and proofs are the same as a fully stored tree.
+    pub async fn initialize_with_options(
+        size: usize,
+        null_value: String,
+        hasher: H,
+        store: Arc<dyn Store>,
+        mmr_id: Option<String>,
+        options: IncrementalMerkleTreeOptions,
+    ) -> Result<Self, IncrementalMerkleTreeError> {
+        let mut tree = IncrementalMerkleTree::new(size, null_value, hasher, store, mmr_id, options);
+        let root = tree.write_empty_tree().await?;
+        tree.root_hash.set(&root, SubKey::None).await?;
+        tree.record_size().await?;
+
+        let (node_hashing_key, domain_separation_key) =
+            IncrementalMerkleTree::<H>::get_hashing_keys(&tree.mmr_id);
+        tree.store
+            .set_many(HashMap::from([
+                (node_hashing_key, tree.node_hashing.to_string()),
+                (domain_separation_key, tree.domain_separation.to_string()),
+                (
+                    IncrementalMerkleTree::<H>::get_lazy_key(&tree.mmr_id),
+                    tree.lazy.to_string(),
+                ),
+                (
+                    format!("{}:{:?}", tree.mmr_id, TreeMetadataKeys::NullValue),
+                    tree.null_value.to_string(),
+                ),
+                (
+                    format!("{}:{:?}", tree.mmr_id, TreeMetadataKeys::Hasher),
+                    tree.hasher.get_name().to_string(),
+                ),
+            ]))
+            .await?;
+        Ok(tree)
+    }
 
-        tree.root_hash
-            .set(&nodes[nodes.len() - 1][0].hash, SubKey::None)
+    /// Reattaches a tree created by [`IncrementalMerkleTree::initialize_with_options`] from its metadata
+    ///
+    /// The recorded hasher must match `hasher`, and the stored root must match the one recomputed
+    /// from the last leaf with the recorded null value, so a tree opened with the wrong
+    /// parameters is rejected instead of corrupted.
+    pub async fn open(
+        store: Arc<dyn Store>,
+        mmr_id: String,
+        hasher: H,
+    ) -> Result<Self, IncrementalMerkleTreeError> {
+        let (node_hashing_key, domain_separation_key) =
+            IncrementalMerkleTree::<H>::get_hashing_keys(&mmr_id);
+        let (size_key, depth_key) = IncrementalMerkleTree::<H>::get_size_keys(&mmr_id);
+        let lazy_key = IncrementalMerkleTree::<H>::get_lazy_key(&mmr_id);
+        let null_value_key = format!("{}:{:?}", mmr_id, TreeMetadataKeys::NullValue);
+        let hasher_key = format!("{}:{:?}", mmr_id, TreeMetadataKeys::Hasher);
+        let metadata = store
+            .get_many(vec![
+                &node_hashing_key,
+                &domain_separation_key,
+                &size_key,
+                &depth_key,
+                &lazy_key,
+                &null_value_key,
+                &hasher_key,
+            ])
             .await?;
+
+        let Some(size) = metadata.get(&size_key) else {
+            return Err(IncrementalMerkleTreeError::TreeNotFound(mmr_id));
+        };
+        let size = size.parse().map_err(|_| {
+            IncrementalMerkleTreeError::InvalidMetadata(format!("invalid size {}", size))
+        })?;
+        let null_value = metadata.get(&null_value_key).ok_or_else(|| {
+            IncrementalMerkleTreeError::InvalidMetadata("null value is missing".to_string())
+        })?;
+        let name = metadata.get(&hasher_key).ok_or_else(|| {
+            IncrementalMerkleTreeError::InvalidMetadata("hasher is missing".to_string())
+        })?;
+        if *name != hasher.get_name().to_string() {
+            return Err(IncrementalMerkleTreeError::InvalidMetadata(format!(
+                "tree was built with {}, not {}",
+                name,
+                hasher.get_name()
+            )));
+        }
+
+        let mut options = IncrementalMerkleTreeOptions::default();
+        if let Some(node_hashing) = metadata.get(&node_hashing_key) {
+            options.node_hashing = NodeHashing::from_str(node_hashing)?;
+        }
+        if let Some(domain_separation) = metadata.get(&domain_separation_key) {
+            options.domain_separation = DomainSeparation::from_str(domain_separation)?;
+        }
+        if let Some(lazy) = metadata.get(&lazy_key) {
+            options.lazy = lazy == "true";
+        }
+
+        let mut tree = IncrementalMerkleTree::new(
+            size,
+            null_value.to_string(),
+            hasher,
+            store,
+            Some(mmr_id),
+            options,
+        );
+        if metadata.get(&depth_key) != Some(&tree.get_tree_depth().to_string()) {
+            return Err(IncrementalMerkleTreeError::InvalidMetadata(
+                "depth does not match the size".to_string(),
+            ));
+        }
+        if tree.lazy {
+            tree.default_nodes = tree.compute_default_nodes()?;
+        }
+
+        if tree.get_root().await? != tree.compute_root_from_last_leaf().await? {
+            return Err(IncrementalMerkleTreeError::InvalidMetadata(
+                "root does not match the stored nodes".to_string(),
+            ));
+        }
         Ok(tree)
     }
 
+    pub fn get_hashing_keys(mmr_id: &str) -> (String, String) {
+        (
+            format!("{}:{:?}", mmr_id, TreeMetadataKeys::NodeHashing),
+            format!("{}:{:?}", mmr_id, TreeMetadataKeys::DomainSeparation),
+        )
+    }
+
+    /// Returns the keys of the recorded size and depth
+    pub fn get_size_keys(mmr_id: &str) -> (String, String) {
+        (
+            format!("{}:{:?}", mmr_id, TreeMetadataKeys::Size),
+            format!("{}:{:?}", mmr_id, TreeMetadataKeys::Depth),
+        )
+    }
+
+    pub fn get_lazy_key(mmr_id: &str) -> String {
+        format!("{}:{:?}", mmr_id, TreeMetadataKeys::Lazy)
+    }
+
+    /// The hashing and storage settings of the tree, as given to [`IncrementalMerkleProof::verify`]
+    pub fn options(&self) -> IncrementalMerkleTreeOptions {
+        IncrementalMerkleTreeOptions {
+            node_hashing: self.node_hashing,
+            domain_separation: self.domain_separation,
+            lazy: self.lazy,
+        }
+    }
+
     pub async fn get_root(&self) -> Result<String, IncrementalMerkleTreeError> {
         self.root_hash
             .get(SubKey::None)
@@ -119,6 +405,7 @@ where
         let mut required_nodes_by_height = Vec::new();
         let tree_depth = self.get_tree_depth();
         let mut current_index = index;
+        let mut level_width = self.size;
 
         for i in (1..=tree_depth).rev() {
             let is_current_index_even = current_index % 2 == 0;
@@ -128,48 +415,78 @@ where
                 current_index - 1
             };
             current_index /= 2;
-            required_nodes_by_height.push((i, neighbour));
+            //? Right siblings past the end of a level are never stored, they hash as the null value
+            required_nodes_by_height.push((i, neighbour, neighbour < level_width));
+            level_width = level_width.div_ceil(2);
         }
 
         let kv_entries: Vec<SubKey> = required_nodes_by_height
             .iter()
-            .map(|(height, index)| SubKey::String(format!("{}:{}", height, index)))
+            .filter(|(_, _, is_stored)| *is_stored)
+            .map(|(height, index, _)| SubKey::String(format!("{}:{}", height, index)))
             .collect();
 
-        let nodes_hash_map = self.nodes.get_many(kv_entries).await?;
+        let nodes_hash_map = self.get_nodes(kv_entries).await?;
 
         let mut ordered_nodes = Vec::with_capacity(required_nodes_by_height.len());
-        for (height, index) in required_nodes_by_height {
-            if let Some(node) = nodes_hash_map.get(&format!("{}:{}", height, index)) {
+        for (height, index, is_stored) in required_nodes_by_height {
+            if !is_stored {
+                ordered_nodes.push(self.null_value.to_string());
+            } else if let Some(node) = nodes_hash_map.get(&format!("{}:{}", height, index)) {
                 ordered_nodes.push(node.to_string());
             }
         }
         Ok(ordered_nodes)
     }
 
-    pub async fn verify_proof(
+    /// Builds an [`IncrementalMerkleProof`] for `value`, which must be the leaf at `index`
+    pub async fn get_proof(
         &self,
         index: usize,
         value: &str,
-        proof: &Vec<String>,
-    ) -> Result<bool, IncrementalMerkleTreeError> {
-        let mut current_index = index;
-        let mut current_value = value.to_string();
+    ) -> Result<IncrementalMerkleProof, IncrementalMerkleTreeError> {
+        if index >= self.size {
+            return Err(IncrementalMerkleTreeError::InvalidIndex);
+        }
 
-        for p in proof {
-            let is_current_index_even = current_index % 2 == 0;
-            current_value = if is_current_index_even {
-                self.hasher
-                    .hash(vec![current_value.to_string(), p.to_string()])?
-            } else {
-                self.hasher
-                    .hash(vec![p.to_string(), current_value.to_string()])?
-            };
-            current_index /= 2;
+        let tree_depth = self.get_tree_depth();
+        let leaf_key = format!("{}:{}", tree_depth, index);
+        let leaf = self
+            .get_nodes(vec![SubKey::String(leaf_key.clone())])
+            .await?
+            .remove(&leaf_key);
+        if leaf != Some(self.hash_leaf(value.to_string())?) {
+            return Err(IncrementalMerkleTreeError::InvalidLeafValue);
         }
 
+        Ok(IncrementalMerkleProof {
+            index,
+            value: value.to_string(),
+            siblings: self.get_inclusion_proof(index).await?,
+            tree_depth,
+            root: self.get_root().await?,
+            node_hashing: self.node_hashing,
+            domain_separation: self.domain_separation,
+        })
+    }
+
+    pub async fn verify_proof(
+        &self,
+        index: usize,
+        value: &str,
+        proof: &[String],
+    ) -> Result<bool, IncrementalMerkleTreeError> {
+        let calculated_root = compute_root(
+            &self.hasher,
+            self.node_hashing,
+            self.domain_separation,
+            index,
+            value,
+            proof,
+        )?;
+
         let root = self.get_root().await?;
-        Ok(root == current_value)
+        Ok(root == calculated_root)
     }
 
     pub async fn update(
@@ -187,7 +504,7 @@ where
         let mut kv_updates: HashMap<SubKey, String> = HashMap::new();
         let mut current_index = index;
         let mut current_depth = self.get_tree_depth();
-        let mut current_value = new_value;
+        let mut current_value = self.hash_leaf(new_value)?;
 
         kv_updates.insert(
             SubKey::String(format!("{}:{}", current_depth, current_index)),
@@ -197,11 +514,9 @@ where
             let is_current_index_even = current_index % 2 == 0;
 
             current_value = if is_current_index_even {
-                self.hasher
-                    .hash(vec![current_value.to_string(), p.to_string()])?
+                self.hash_pair(current_value, p.to_string())?
             } else {
-                self.hasher
-                    .hash(vec![p.to_string(), current_value.to_string()])?
+                self.hash_pair(p.to_string(), current_value)?
             };
 
             current_depth -= 1;
@@ -215,11 +530,260 @@ where
             );
         }
 
-        self.nodes.set_many(kv_updates).await?;
+        self.set_nodes(kv_updates).await?;
         self.root_hash.set(&current_value, SubKey::None).await?;
         Ok(current_value)
     }
 
+    /// Sets the leaves at the given indexes, the last value wins for repeated indexes
+    ///
+    /// Unlike [`IncrementalMerkleTree::update`] no proof is needed: the untouched siblings are
+    /// loaded in a single read, each changed node is hashed once and all of them are written
+    /// in a single write. Returns the new root.
+    pub async fn update_many(
+        &self,
+        updates: Vec<(usize, String)>,
+    ) -> Result<String, IncrementalMerkleTreeError> {
+        let mut leaves: BTreeMap<usize, String> = BTreeMap::new();
+        for (index, value) in updates {
+            if index >= self.size {
+                return Err(IncrementalMerkleTreeError::InvalidIndex);
+            }
+            leaves.insert(index, self.hash_leaf(value)?);
+        }
+
+        self.set_leaf_hashes(leaves).await
+    }
+
+    /// Grows the tree to `new_size` leaves, keeping the existing leaves at their indexes
+    ///
+    /// Every node moves to its new depth, so the root is the one of a new tree of `new_size`
+    /// holding the same leaves. The new nodes, root, size and depth are written in one batch
+    /// before the nodes of the old layout are deleted, so a failure leaves a usable tree.
+    pub async fn resize(&mut self, new_size: usize) -> Result<String, IncrementalMerkleTreeError> {
+        if new_size < self.size {
+            return Err(IncrementalMerkleTreeError::InvalidSize);
+        }
+
+        //? Only the leaves are read, every other node is recomputed
+        let tree_depth = self.get_tree_depth();
+        let null_leaf = self.hash_leaf(self.null_value.to_string())?;
+        let leaves_prefix =
+            InStoreTable::get_full_key(&self.nodes.key, &format!("{}:", tree_depth));
+        let stored_leaves: Vec<(String, String)> =
+            self.store.scan_prefix(&leaves_prefix).try_collect().await?;
+        let mut leaves = BTreeMap::new();
+        for (key, hash) in stored_leaves {
+            let index: usize = key
+                .strip_prefix(&leaves_prefix)
+                .and_then(|index| index.parse().ok())
+                .ok_or(IncrementalMerkleTreeError::InvalidIndex)?;
+            if hash != null_leaf {
+                leaves.insert(index, hash);
+            }
+        }
+        let old_sub_keys = self.get_stored_sub_keys(leaves.keys().copied());
+
+        let (old_size, old_default_nodes) = (self.size, self.default_nodes.clone());
+        self.size = new_size;
+        let batch = match self.compute_resized_nodes(leaves, &old_sub_keys) {
+            Ok(batch) => batch,
+            Err(error) => {
+                (self.size, self.default_nodes) = (old_size, old_default_nodes);
+                return Err(error);
+            }
+        };
+        let (root, mut entries, stale_keys) = batch;
+
+        let (size_key, depth_key) = IncrementalMerkleTree::<H>::get_size_keys(&self.mmr_id);
+        entries.insert(
+            InStoreTable::get_full_key(&self.root_hash.key, &SubKey::None.to_string()),
+            root.clone(),
+        );
+        entries.insert(size_key, new_size.to_string());
+        entries.insert(depth_key, self.get_tree_depth().to_string());
+        if let Err(error) = self.store.set_many(entries).await {
+            (self.size, self.default_nodes) = (old_size, old_default_nodes);
+            return Err(error.into());
+        }
+
+        self.store
+            .delete_many(stale_keys.iter().map(AsRef::as_ref).collect())
+            .await?;
+        Ok(root)
+    }
+
+    /// The `depth:index` sub keys a tree stores, given the indexes of its non-empty leaves
+    fn get_stored_sub_keys(&self, leaf_indexes: impl Iterator<Item = usize>) -> Vec<String> {
+        let tree_depth = self.get_tree_depth();
+        if !self.lazy {
+            return (0..=tree_depth)
+                .flat_map(|depth| {
+                    (0..self.get_level_width(depth))
+                        .map(move |index| format!("{}:{}", depth, index))
+                })
+                .collect();
+        }
+
+        //? A lazy tree only stores the paths from its non-empty leaves
+        let mut sub_keys = std::collections::BTreeSet::new();
+        for leaf_index in leaf_indexes {
+            for depth in 0..=tree_depth {
+                sub_keys.insert(format!("{}:{}", depth, leaf_index >> (tree_depth - depth)));
+            }
+        }
+        sub_keys.into_iter().collect()
+    }
+
+    /// Computes the nodes of the tree at its current size holding `leaves`
+    ///
+    /// Returns the root, the full keys to write and the keys of `old_sub_keys` to delete once
+    /// written. Until then, a lazy tree holds their new default value.
+    #[allow(clippy::type_complexity)]
+    fn compute_resized_nodes(
+        &mut self,
+        leaves: BTreeMap<usize, String>,
+        old_sub_keys: &[String],
+    ) -> Result<(String, HashMap<String, String>, Vec<String>), IncrementalMerkleTreeError> {
+        self.default_nodes = self.compute_default_nodes()?;
+
+        let mut nodes: HashMap<String, String> = HashMap::new();
+        if !self.lazy {
+            for node in self.render_empty_tree()?.into_iter().flatten() {
+                nodes.insert(format!("{}:{}", node.depth, node.index), node.hash);
+            }
+        }
+        let root = if leaves.is_empty() {
+            self.default_nodes[0].1.clone()
+        } else {
+            let (updates, root) =
+                self.hash_leaves_up(leaves, |sub_key| self.get_default_node(sub_key))?;
+            for (sub_key, hash) in updates {
+                if !self.lazy || hash != self.get_default_node(&sub_key)? {
+                    nodes.insert(sub_key, hash);
+                }
+            }
+            root
+        };
+
+        let mut stale_keys = Vec::new();
+        for sub_key in old_sub_keys {
+            if nodes.contains_key(sub_key) {
+                continue;
+            }
+            let full_key = InStoreTable::get_full_key(&self.nodes.key, sub_key);
+            if self.lazy {
+                nodes.insert(sub_key.clone(), self.get_default_node(sub_key)?);
+            }
+            stale_keys.push(full_key);
+        }
+
+        let entries = nodes
+            .into_iter()
+            .map(|(sub_key, hash)| (InStoreTable::get_full_key(&self.nodes.key, &sub_key), hash))
+            .collect();
+        Ok((root, entries, stale_keys))
+    }
+
+    /// Adds a level to the tree, doubling the number of leaves it can hold
+    pub async fn grow_depth(&mut self) -> Result<String, IncrementalMerkleTreeError> {
+        let new_size = 1usize
+            .checked_shl(self.get_tree_depth() as u32 + 1)
+            .ok_or(IncrementalMerkleTreeError::InvalidSize)?;
+        self.resize(new_size).await
+    }
+
+    /// Sets already hashed leaves, recomputing each of their ancestors once
+    async fn set_leaf_hashes(
+        &self,
+        changed: BTreeMap<usize, String>,
+    ) -> Result<String, IncrementalMerkleTreeError> {
+        if changed.is_empty() {
+            return self.get_root().await;
+        }
+
+        let tree_depth = self.get_tree_depth();
+
+        //? Siblings that are not recomputed, level by level, from the leaves up
+        let mut siblings_to_load = Vec::new();
+        let mut level_indexes: Vec<usize> = changed.keys().copied().collect();
+        let mut level_width = self.size;
+        for depth in (1..=tree_depth).rev() {
+            for &index in &level_indexes {
+                let sibling = index ^ 1;
+                if sibling < level_width && level_indexes.binary_search(&sibling).is_err() {
+                    siblings_to_load.push(SubKey::String(format!("{}:{}", depth, sibling)));
+                }
+            }
+            level_indexes = level_indexes.iter().map(|index| index / 2).collect();
+            level_indexes.dedup();
+            level_width = level_width.div_ceil(2);
+        }
+        let loaded_siblings = self.get_nodes(siblings_to_load).await?;
+
+        let (updates, root) = self.hash_leaves_up(changed, |sub_key| {
+            Ok(loaded_siblings
+                .get(sub_key)
+                .cloned()
+                .unwrap_or_else(|| self.null_value.to_string()))
+        })?;
+        self.set_nodes(
+            updates
+                .into_iter()
+                .map(|(sub_key, hash)| (SubKey::String(sub_key), hash))
+                .collect(),
+        )
+        .await?;
+        self.root_hash.set(&root, SubKey::None).await?;
+        Ok(root)
+    }
+
+    /// Hashes changed leaves up to the root, reading the untouched siblings with `get_sibling`
+    ///
+    /// Returns the changed nodes by `depth:index` sub key, and the root.
+    fn hash_leaves_up(
+        &self,
+        mut changed: BTreeMap<usize, String>,
+        get_sibling: impl Fn(&str) -> Result<String, IncrementalMerkleTreeError>,
+    ) -> Result<(HashMap<String, String>, String), IncrementalMerkleTreeError> {
+        let tree_depth = self.get_tree_depth();
+        let mut updates: HashMap<String, String> = HashMap::new();
+        for depth in (1..=tree_depth).rev() {
+            let mut parents = BTreeMap::new();
+            for (&index, hash) in &changed {
+                updates.insert(format!("{}:{}", depth, index), hash.clone());
+
+                let parent = index / 2;
+                if parents.contains_key(&parent) {
+                    continue;
+                }
+                let sibling = index ^ 1;
+                let sibling_hash = match changed.get(&sibling) {
+                    Some(sibling_hash) => sibling_hash.clone(),
+                    None => get_sibling(&format!("{}:{}", depth, sibling))?,
+                };
+                let hash = if index % 2 == 0 {
+                    self.hash_pair(hash.clone(), sibling_hash)?
+                } else {
+                    self.hash_pair(sibling_hash, hash.clone())?
+                };
+                parents.insert(parent, hash);
+            }
+            changed = parents;
+        }
+
+        //? Only the root is left, it is stored under its own key
+        let root = changed
+            .into_values()
+            .next()
+            .ok_or_else(|| IncrementalMerkleTreeError::RootHashNotFound(self.mmr_id.to_string()))?;
+        //? With a single leaf, the root is also the leaf node
+        if tree_depth == 0 {
+            updates.insert("0:0".to_string(), root.clone());
+        }
+        Ok((updates, root))
+    }
+
     pub async fn get_inclusion_multi_proof(
         &self,
         indexes_to_prove: Vec<usize>,
@@ -286,7 +850,7 @@ where
             })
             .collect();
 
-        let nodes_hash_map = self.nodes.get_many(kv_entries.clone()).await?;
+        let nodes_hash_map = self.get_nodes(kv_entries.clone()).await?;
 
         let mut nodes_values: Vec<String> = Vec::with_capacity(kv_entries.len());
         for kv in kv_entries {
@@ -305,7 +869,11 @@ where
         proof: &mut Vec<String>,
     ) -> Result<bool, IncrementalMerkleTreeError> {
         let root = self.get_root().await?;
-        let calculated_root = self.calculate_multiproof_root_hash(indexes, values, proof)?;
+        let mut leaves = values
+            .drain(..)
+            .map(|value| self.hash_leaf(value))
+            .collect::<Result<Vec<String>, HasherError>>()?;
+        let calculated_root = self.calculate_multiproof_root_hash(indexes, &mut leaves, proof)?;
 
         Ok(root == calculated_root)
     }
@@ -339,9 +907,9 @@ where
             }
 
             let hash = if is_even {
-                self.hasher.hash(vec![value, wanted_value])?
+                self.hash_pair(value, wanted_value)?
             } else {
-                self.hasher.hash(vec![wanted_value, value])?
+                self.hash_pair(wanted_value, value)?
             };
 
             new_indexes.push(index / 2);
@@ -358,16 +926,225 @@ where
             .ok_or_else(|| IncrementalMerkleTreeError::RootHashNotFound(self.mmr_id.to_string()))
     }
 
+    /// Recomputes the root along the path of the last leaf, from the leaf level up
+    ///
+    /// Its siblings past the end of a level, and the missing nodes of a lazy tree, are derived
+    /// from `null_value`, so this also checks the recorded null value.
+    async fn compute_root_from_last_leaf(&self) -> Result<String, IncrementalMerkleTreeError> {
+        let tree_depth = self.get_tree_depth();
+        let leaf_index = self.size.saturating_sub(1);
+
+        let mut sub_keys = vec![format!("{}:{}", tree_depth, leaf_index)];
+        for depth in (1..=tree_depth).rev() {
+            let sibling = (leaf_index >> (tree_depth - depth)) ^ 1;
+            if sibling < self.get_level_width(depth) {
+                sub_keys.push(format!("{}:{}", depth, sibling));
+            }
+        }
+        let mut nodes = self
+            .get_nodes(sub_keys.iter().cloned().map(SubKey::String).collect())
+            .await?;
+        if let Some(missing) = sub_keys
+            .iter()
+            .find(|sub_key| !nodes.contains_key(*sub_key))
+        {
+            return Err(IncrementalMerkleTreeError::InvalidMetadata(format!(
+                "node {} is missing",
+                missing
+            )));
+        }
+
+        let mut hash = nodes.remove(&sub_keys[0]).unwrap_or_default();
+        for depth in (1..=tree_depth).rev() {
+            let index = leaf_index >> (tree_depth - depth);
+            let sibling_hash = nodes
+                .remove(&format!("{}:{}", depth, index ^ 1))
+                .unwrap_or_else(|| self.null_value.to_string());
+            hash = if index % 2 == 0 {
+                self.hash_pair(hash, sibling_hash)?
+            } else {
+                self.hash_pair(sibling_hash, hash)?
+            };
+        }
+        Ok(hash)
+    }
+
+    /// Writes the nodes of an empty tree of the current size, or only computes the defaults
+    /// of a lazy tree, and returns the empty root
+    async fn write_empty_tree(&mut self) -> Result<String, IncrementalMerkleTreeError> {
+        if self.lazy {
+            self.default_nodes = self.compute_default_nodes()?;
+            return Ok(self.default_nodes[0].1.clone());
+        }
+
+        let nodes = self.render_empty_tree()?;
+        let nodes_hashmap: HashMap<SubKey, String> =
+            nodes
+                .iter()
+                .flatten()
+                .fold(HashMap::new(), |mut acc, curr| {
+                    let key = SubKey::String(format!("{}:{}", curr.depth, curr.index));
+                    acc.insert(key, curr.hash.clone());
+                    acc
+                });
+
+        self.nodes.set_many(nodes_hashmap).await?;
+        Ok(nodes[nodes.len() - 1][0].hash.clone())
+    }
+
+    async fn record_size(&self) -> Result<(), IncrementalMerkleTreeError> {
+        let (size_key, depth_key) = IncrementalMerkleTree::<H>::get_size_keys(&self.mmr_id);
+        self.store
+            .set_many(HashMap::from([
+                (size_key, self.size.to_string()),
+                (depth_key, self.get_tree_depth().to_string()),
+            ]))
+            .await?;
+        Ok(())
+    }
+
+    /// Reads nodes by `depth:index` sub key, the nodes a lazy tree does not store resolve to their defaults
+    async fn get_nodes(
+        &self,
+        sub_keys: Vec<SubKey>,
+    ) -> Result<HashMap<String, String>, IncrementalMerkleTreeError> {
+        if !self.lazy {
+            return Ok(self.nodes.get_many(sub_keys).await?);
+        }
+
+        let full_keys: Vec<String> = sub_keys
+            .iter()
+            .map(|sub_key| InStoreTable::get_full_key(&self.nodes.key, &sub_key.to_string()))
+            .collect();
+        let mut fetched = self
+            .store
+            .get_many(full_keys.iter().map(AsRef::as_ref).collect())
+            .await?;
+
+        let mut nodes = HashMap::with_capacity(sub_keys.len());
+        for (sub_key, full_key) in sub_keys.into_iter().zip(full_keys) {
+            let sub_key = sub_key.to_string();
+            let hash = match fetched.remove(&full_key) {
+                Some(hash) => hash,
+                None => self.get_default_node(&sub_key)?,
+            };
+            nodes.insert(sub_key, hash);
+        }
+        Ok(nodes)
+    }
+
+    /// Writes nodes, a lazy tree deletes the ones that are back to their defaults instead
+    async fn set_nodes(
+        &self,
+        nodes: HashMap<SubKey, String>,
+    ) -> Result<(), IncrementalMerkleTreeError> {
+        if !self.lazy {
+            return Ok(self.nodes.set_many(nodes).await?);
+        }
+
+        let mut entries = HashMap::new();
+        let mut defaults = Vec::new();
+        for (sub_key, hash) in nodes {
+            let sub_key = sub_key.to_string();
+            let full_key = InStoreTable::get_full_key(&self.nodes.key, &sub_key);
+            if hash == self.get_default_node(&sub_key)? {
+                defaults.push(full_key);
+            } else {
+                entries.insert(full_key, hash);
+            }
+        }
+
+        if !defaults.is_empty() {
+            self.store
+                .delete_many(defaults.iter().map(AsRef::as_ref).collect())
+                .await?;
+        }
+        self.store.set_many(entries).await?;
+        Ok(())
+    }
+
+    /// The hash of the node at `depth:index` in the empty tree
+    fn get_default_node(&self, sub_key: &str) -> Result<String, IncrementalMerkleTreeError> {
+        let (depth, index): (usize, usize) = sub_key
+            .split_once(':')
+            .and_then(|(depth, index)| Some((depth.parse().ok()?, index.parse().ok()?)))
+            .ok_or(IncrementalMerkleTreeError::InvalidIndex)?;
+        let (default_node, last_default_node) = self
+            .default_nodes
+            .get(depth)
+            .ok_or(IncrementalMerkleTreeError::InvalidIndex)?;
+
+        let level_width = self.get_level_width(depth);
+        Ok(match index {
+            index if index + 1 < level_width => default_node.clone(),
+            index if index + 1 == level_width => last_default_node.clone(),
+            //? Past the end of a level, like the missing right siblings of `render_empty_tree`
+            _ => self.null_value.to_string(),
+        })
+    }
+
+    /// Computes the empty nodes of each depth without rendering the tree
+    ///
+    /// The last node of a level can cover leaves past `size`, so it is tracked separately.
+    fn compute_default_nodes(&self) -> Result<Vec<(String, String)>, IncrementalMerkleTreeError> {
+        let tree_depth = self.get_tree_depth();
+        let null_leaf = self.hash_leaf(self.null_value.to_string())?;
+        let mut default_nodes = vec![(null_leaf.clone(), null_leaf)];
+
+        for depth in (0..tree_depth).rev() {
+            let (child, last_child) = default_nodes.last().unwrap();
+            let children_width = self.get_level_width(depth + 1);
+            let last_index = self.get_level_width(depth) - 1;
+
+            let left = if 2 * last_index + 1 == children_width {
+                last_child.clone()
+            } else {
+                child.clone()
+            };
+            let right = if 2 * last_index + 1 < children_width {
+                last_child.clone()
+            } else {
+                self.null_value.to_string()
+            };
+            let default_nodes_at_depth = (
+                self.hash_pair(child.clone(), child.clone())?,
+                self.hash_pair(left, right)?,
+            );
+            default_nodes.push(default_nodes_at_depth);
+        }
+
+        default_nodes.reverse();
+        Ok(default_nodes)
+    }
+
+    fn get_level_width(&self, depth: usize) -> usize {
+        let levels_above_leaves = (self.get_tree_depth() - depth) as u32;
+        match 1usize.checked_shl(levels_above_leaves) {
+            Some(leaves_per_node) => self.size.div_ceil(leaves_per_node),
+            None => 1,
+        }
+    }
+
+    fn hash_leaf(&self, value: String) -> Result<String, HasherError> {
+        self.domain_separation.hash_leaf(&self.hasher, value)
+    }
+
+    fn hash_pair(&self, left: String, right: String) -> Result<String, HasherError> {
+        self.domain_separation
+            .hash_node(&self.hasher, self.node_hashing, left, right)
+    }
+
     fn get_tree_depth(&self) -> usize {
-        (self.size as f64).log2().ceil() as usize
+        self.size.next_power_of_two().trailing_zeros() as usize
     }
 
     fn render_empty_tree(&self) -> Result<Vec<Vec<Node>>, IncrementalMerkleTreeError> {
         let mut current_height_nodes_count = self.size;
         let mut current_depth = self.get_tree_depth();
+        let null_leaf = self.hash_leaf(self.null_value.to_string())?;
         let mut tree: Vec<Vec<Node>> = vec![(0..self.size)
             .map(|index| Node {
-                hash: self.null_value.to_string(),
+                hash: null_leaf.clone(),
                 index,
                 depth: current_depth,
             })
@@ -384,9 +1161,7 @@ where
                     .map_or(self.null_value.to_string(), |node| node.hash.clone());
 
                 let node = Node {
-                    hash: self
-                        .hasher
-                        .hash(vec![left_sibling.to_string(), right_sibling.to_string()])?,
+                    hash: self.hash_pair(left_sibling.to_string(), right_sibling)?,
                     index: i / 2,
                     depth: current_depth,
                 };
@@ -399,3 +1174,28 @@ where
         Ok(tree)
     }
 }
+
+/// Hashes `value` up to the root along `siblings`, ordered from the leaf
+fn compute_root<H: Hasher + ?Sized>(
+    hasher: &H,
+    node_hashing: NodeHashing,
+    domain_separation: DomainSeparation,
+    index: usize,
+    value: &str,
+    siblings: &[String],
+) -> Result<String, HasherError> {
+    let mut current_index = index;
+    let mut current_value = domain_separation.hash_leaf(hasher, value.to_string())?;
+
+    for sibling in siblings {
+        let (left, right) = if current_index % 2 == 0 {
+            (current_value, sibling.to_string())
+        } else {
+            (sibling.to_string(), current_value)
+        };
+        current_value = domain_separation.hash_node(hasher, node_hashing, left, right)?;
+        current_index /= 2;
+    }
+
+    Ok(current_value)
+}
@@ -1,2 +1,4 @@
 #[cfg(feature = "incremental_merkle_tree")]
 pub mod incremental;
+#[cfg(feature = "static_merkle_tree")]
+pub mod static_tree;
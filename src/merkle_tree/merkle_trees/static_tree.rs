@@ -0,0 +1,365 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use thiserror::Error;
+
+use crate::hasher::{compare_hashes, Hasher, HasherError, NodeHashing};
+
+/// Error for Static Merkle Tree
+#[derive(Error, Debug)]
+pub enum StaticMerkleTreeError {
+    #[error("Cannot build a tree without leaves")]
+    EmptyTree,
+    #[error("Invalid index")]
+    InvalidIndex,
+    #[error("Multiproofs require sorted pairs")]
+    MultiProofRequiresSortedPairs,
+    #[error("Invalid multiproof")]
+    InvalidMultiProof,
+    #[error("Cannot prove a duplicated index")]
+    DuplicatedIndex,
+    #[error("Hasher error: {0}")]
+    HasherError(#[from] HasherError),
+}
+
+/// How a [`StaticMerkleTree`] hashes its leaves and nodes
+///
+/// The default matches OpenZeppelin's `StandardMerkleTree`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticMerkleTreeOptions {
+    /// Hash each pair of nodes in ascending order instead of by position
+    pub sort_pairs: bool,
+    /// Place the leaves in the tree ordered by hash instead of in the given order
+    pub sort_leaves: bool,
+    /// Hash every leaf twice, which prevents second preimage attacks when leaves are 64 bytes long
+    pub double_hash_leaves: bool,
+}
+
+impl Default for StaticMerkleTreeOptions {
+    fn default() -> Self {
+        Self {
+            sort_pairs: true,
+            sort_leaves: true,
+            double_hash_leaves: true,
+        }
+    }
+}
+
+/// A proof that a leaf is part of a [`StaticMerkleTree`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticMerkleProof {
+    pub leaf_hash: String,
+    /// The position of the leaf in the tree array, only needed without sorted pairs
+    pub tree_index: usize,
+    /// The siblings from the leaf up to the root, as expected by `MerkleProof.verify`
+    pub siblings: Vec<String>,
+}
+
+/// A proof that several leaves are part of a [`StaticMerkleTree`], as expected by `MerkleProof.multiProofVerify`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticMerkleMultiProof {
+    pub leaves: Vec<String>,
+    pub proof: Vec<String>,
+    pub proof_flags: Vec<bool>,
+}
+
+/// A Merkle tree built once from a list of leaves and kept in memory
+///
+/// Nodes are laid out like OpenZeppelin's `merkle-tree` library: a complete binary tree stored in
+/// an array, with the root first and the leaves at the end, so its proofs can be verified by
+/// OpenZeppelin's `MerkleProof` contract when used with the keccak hasher and sorted pairs.
+#[derive(Debug, Clone)]
+pub struct StaticMerkleTree {
+    pub hasher: Arc<dyn Hasher>,
+    pub options: StaticMerkleTreeOptions,
+    tree: Vec<String>,
+    //? Position in `tree` of each leaf, in the order the leaves were given
+    leaves_tree_indexes: Vec<usize>,
+}
+
+impl StaticMerkleTree {
+    pub fn new(
+        leaves: Vec<String>,
+        hasher: Arc<dyn Hasher>,
+        options: StaticMerkleTreeOptions,
+    ) -> Result<Self, StaticMerkleTreeError> {
+        if leaves.is_empty() {
+            return Err(StaticMerkleTreeError::EmptyTree);
+        }
+
+        let mut hashed_leaves = leaves
+            .iter()
+            .enumerate()
+            .map(|(leaf_index, value)| {
+                Ok((
+                    leaf_index,
+                    leaf_hash(hasher.as_ref(), value, options.double_hash_leaves)?,
+                ))
+            })
+            .collect::<Result<Vec<(usize, String)>, StaticMerkleTreeError>>()?;
+        if options.sort_leaves {
+            let mut sort_error = None;
+            hashed_leaves.sort_by(|(_, a), (_, b)| {
+                compare_hashes(a, b).unwrap_or_else(|error| {
+                    sort_error.get_or_insert(error);
+                    Ordering::Equal
+                })
+            });
+            if let Some(error) = sort_error {
+                return Err(error.into());
+            }
+        }
+
+        let tree_size = 2 * leaves.len() - 1;
+        let mut tree = vec![String::new(); tree_size];
+        let mut leaves_tree_indexes = vec![0; leaves.len()];
+        for (position, (leaf_index, hash)) in hashed_leaves.into_iter().enumerate() {
+            let tree_index = tree_size - 1 - position;
+            tree[tree_index] = hash;
+            leaves_tree_indexes[leaf_index] = tree_index;
+        }
+
+        for tree_index in (0..tree_size - leaves.len()).rev() {
+            tree[tree_index] = hash_pair(
+                hasher.as_ref(),
+                &tree[left_child_index(tree_index)],
+                &tree[left_child_index(tree_index) + 1],
+                options.sort_pairs,
+            )?;
+        }
+
+        Ok(Self {
+            hasher,
+            options,
+            tree,
+            leaves_tree_indexes,
+        })
+    }
+
+    pub fn root(&self) -> &str {
+        &self.tree[0]
+    }
+
+    pub fn leaves_count(&self) -> usize {
+        self.leaves_tree_indexes.len()
+    }
+
+    /// The hash of the leaf at `leaf_index`, in the order the leaves were given
+    pub fn get_leaf_hash(&self, leaf_index: usize) -> Result<&str, StaticMerkleTreeError> {
+        Ok(&self.tree[self.get_tree_index(leaf_index)?])
+    }
+
+    pub fn get_proof(&self, leaf_index: usize) -> Result<StaticMerkleProof, StaticMerkleTreeError> {
+        let tree_index = self.get_tree_index(leaf_index)?;
+
+        let mut siblings = Vec::new();
+        let mut current_index = tree_index;
+        while current_index > 0 {
+            siblings.push(self.tree[sibling_index(current_index)].clone());
+            current_index = parent_index(current_index);
+        }
+
+        Ok(StaticMerkleProof {
+            leaf_hash: self.tree[tree_index].clone(),
+            tree_index,
+            siblings,
+        })
+    }
+
+    pub fn verify_proof(&self, proof: &StaticMerkleProof) -> Result<bool, StaticMerkleTreeError> {
+        Ok(self.process_proof(proof)? == self.root())
+    }
+
+    /// Computes the root a proof leads to, see [`process_proof`]
+    pub fn process_proof(
+        &self,
+        proof: &StaticMerkleProof,
+    ) -> Result<String, StaticMerkleTreeError> {
+        process_proof(self.hasher.as_ref(), proof, self.options.sort_pairs)
+    }
+
+    /// Builds a multiproof for the leaves at `leaf_indexes`, only available with sorted pairs
+    ///
+    /// Like OpenZeppelin's `getMultiProof`, each leaf can only be requested once.
+    pub fn get_multi_proof(
+        &self,
+        leaf_indexes: &[usize],
+    ) -> Result<StaticMerkleMultiProof, StaticMerkleTreeError> {
+        if !self.options.sort_pairs {
+            return Err(StaticMerkleTreeError::MultiProofRequiresSortedPairs);
+        }
+
+        let mut tree_indexes = leaf_indexes
+            .iter()
+            .map(|&leaf_index| self.get_tree_index(leaf_index))
+            .collect::<Result<Vec<usize>, StaticMerkleTreeError>>()?;
+        tree_indexes.sort_unstable_by(|a, b| b.cmp(a));
+        if tree_indexes.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(StaticMerkleTreeError::DuplicatedIndex);
+        }
+
+        let mut stack: std::collections::VecDeque<usize> = tree_indexes.iter().copied().collect();
+        let mut proof = Vec::new();
+        let mut proof_flags = Vec::new();
+        while let Some(&current_index) = stack.front() {
+            if current_index == 0 {
+                break;
+            }
+            stack.pop_front();
+
+            let sibling = sibling_index(current_index);
+            if stack.front() == Some(&sibling) {
+                proof_flags.push(true);
+                stack.pop_front();
+            } else {
+                proof_flags.push(false);
+                proof.push(self.tree[sibling].clone());
+            }
+            stack.push_back(parent_index(current_index));
+        }
+        if tree_indexes.is_empty() {
+            proof.push(self.root().to_string());
+        }
+
+        Ok(StaticMerkleMultiProof {
+            leaves: tree_indexes
+                .into_iter()
+                .map(|tree_index| self.tree[tree_index].clone())
+                .collect(),
+            proof,
+            proof_flags,
+        })
+    }
+
+    pub fn verify_multi_proof(
+        &self,
+        multi_proof: &StaticMerkleMultiProof,
+    ) -> Result<bool, StaticMerkleTreeError> {
+        Ok(process_multi_proof(self.hasher.as_ref(), multi_proof)? == self.root())
+    }
+
+    fn get_tree_index(&self, leaf_index: usize) -> Result<usize, StaticMerkleTreeError> {
+        self.leaves_tree_indexes
+            .get(leaf_index)
+            .copied()
+            .ok_or(StaticMerkleTreeError::InvalidIndex)
+    }
+}
+
+/// Hashes a leaf value the way a [`StaticMerkleTree`] does
+pub fn leaf_hash(
+    hasher: &dyn Hasher,
+    value: &str,
+    double_hash: bool,
+) -> Result<String, StaticMerkleTreeError> {
+    if double_hash {
+        Ok(hasher.hash_single(&hasher.hash_single(value)?)?)
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// Computes the root a proof leads to, like `MerkleProof.processProof`
+///
+/// `sort_pairs` must match the option of the tree the proof comes from. Without it, the
+/// position of each node is taken from `proof.tree_index`.
+pub fn process_proof(
+    hasher: &dyn Hasher,
+    proof: &StaticMerkleProof,
+    sort_pairs: bool,
+) -> Result<String, StaticMerkleTreeError> {
+    let mut current_index = proof.tree_index;
+    let mut hash = proof.leaf_hash.clone();
+    for sibling in &proof.siblings {
+        //? Left children are at odd indexes
+        hash = if current_index % 2 == 1 {
+            hash_pair(hasher, &hash, sibling, sort_pairs)?
+        } else {
+            hash_pair(hasher, sibling, &hash, sort_pairs)?
+        };
+        current_index = parent_index(current_index);
+    }
+
+    Ok(hash)
+}
+
+/// Computes the root a multiproof leads to, like `MerkleProof.processMultiProof`
+pub fn process_multi_proof(
+    hasher: &dyn Hasher,
+    multi_proof: &StaticMerkleMultiProof,
+) -> Result<String, StaticMerkleTreeError> {
+    let StaticMerkleMultiProof {
+        leaves,
+        proof,
+        proof_flags,
+    } = multi_proof;
+    if leaves.len() + proof.len() != proof_flags.len() + 1 {
+        return Err(StaticMerkleTreeError::InvalidMultiProof);
+    }
+
+    let mut hashes: Vec<String> = Vec::with_capacity(proof_flags.len());
+    let (mut leaf_position, mut hash_position, mut proof_position) = (0, 0, 0);
+    let mut next_known = |hashes: &Vec<String>| {
+        let known = if leaf_position < leaves.len() {
+            leaf_position += 1;
+            leaves.get(leaf_position - 1)
+        } else {
+            hash_position += 1;
+            hashes.get(hash_position - 1)
+        };
+        known
+            .cloned()
+            .ok_or(StaticMerkleTreeError::InvalidMultiProof)
+    };
+
+    for &flag in proof_flags {
+        let a = next_known(&hashes)?;
+        let b = if flag {
+            next_known(&hashes)?
+        } else {
+            proof_position += 1;
+            proof
+                .get(proof_position - 1)
+                .cloned()
+                .ok_or(StaticMerkleTreeError::InvalidMultiProof)?
+        };
+        hashes.push(hash_pair(hasher, &a, &b, true)?);
+    }
+
+    match (hashes.pop(), leaves.first(), proof.first()) {
+        (Some(root), _, _) if proof_position == proof.len() => Ok(root),
+        (Some(_), _, _) => Err(StaticMerkleTreeError::InvalidMultiProof),
+        (None, Some(leaf), _) => Ok(leaf.clone()),
+        (None, None, Some(root)) => Ok(root.clone()),
+        (None, None, None) => Err(StaticMerkleTreeError::InvalidMultiProof),
+    }
+}
+
+fn hash_pair(
+    hasher: &dyn Hasher,
+    left: &str,
+    right: &str,
+    sort_pairs: bool,
+) -> Result<String, StaticMerkleTreeError> {
+    let node_hashing = if sort_pairs {
+        NodeHashing::Sorted
+    } else {
+        NodeHashing::Positional
+    };
+    Ok(node_hashing.hash_pair(hasher, left.to_string(), right.to_string())?)
+}
+
+fn left_child_index(tree_index: usize) -> usize {
+    2 * tree_index + 1
+}
+
+fn parent_index(tree_index: usize) -> usize {
+    (tree_index - 1) / 2
+}
+
+fn sibling_index(tree_index: usize) -> usize {
+    if tree_index % 2 == 1 {
+        tree_index + 1
+    } else {
+        tree_index - 1
+    }
+}
@@ -1,22 +1,27 @@
-use std::collections::{HashMap, VecDeque};
+use futures::TryStreamExt;
+use std::collections::{BTreeSet, HashMap};
 use std::str::FromStr;
 use std::sync::Arc;
 use thiserror::Error;
 use uuid::Uuid;
 
-use crate::hasher::{Hasher, HasherError, HashingFunction};
-use crate::store::{InStoreCounter, InStoreTable, InStoreTableError, Store, StoreError, SubKey};
+use crate::hasher::{DomainSeparation, Hasher, HasherError, HashingFunction, NodeHashing};
+#[cfg(feature = "stacked_mmr")]
+use crate::store::SubMMR;
+use crate::store::{
+    InStoreCounter, InStoreTable, InStoreTableError, IntoDynStore, Store, StoreError, SubKey,
+};
 
 use crate::mmr::{
     formatting::{format_peaks, format_proof, PeaksFormattingOptions},
     helpers::{
-        array_deduplicate, element_index_to_leaf_index, find_peaks, find_siblings, get_peak_info,
+        array_deduplicate, compute_peak_hash, find_peaks, find_siblings, get_peak_info,
         leaf_count_to_append_no_merges, leaf_count_to_peaks_count, mmr_size_to_leaf_count,
-        AppendResult, Proof, ProofOptions, TreeMetadataKeys,
+        AppendResult, HashingOptions, PeakBagging, Proof, ProofOptions, TreeMetadataKeys,
     },
 };
 
-use super::{FormattingError, PeaksOptions, TreeMetadataKeysError};
+use super::{FormattingError, PeaksOptions, PruningStateCache, TreeMetadataKeysError};
 
 /// An error that can occur when using an MMR
 #[derive(Error, Debug)]
@@ -41,19 +46,75 @@ pub enum MMRError {
     Formatting(#[from] FormattingError),
     #[error("No hash found for index {0}")]
     NoHashFoundForIndex(usize),
+    #[error("Element {0} was pruned")]
+    PrunedElement(usize),
+    #[error("Draft diff does not match the MMR it is applied to")]
+    InvalidDraftDiff,
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+    #[error("Leaves hashed with domain separation cannot be searched by key")]
+    TaggedLeavesNotSearchable,
+    #[error("Invalid range, start is after end")]
+    InvalidRange,
+    #[error("Key {0} is present")]
+    KeyPresent(String),
+    #[error("Invalid peak bagging")]
+    InvalidPeakBagging,
+    #[error("Stacked MMRs cannot be pruned")]
+    StackedPruning,
+    #[error("The {0} does not match the one recorded for this MMR")]
+    HashingMetadataMismatch(String),
 }
 
+/// A Merkle Mountain Range over a hasher and a store
+///
+/// The hasher and store default to trait objects, so a plain `MMR` is the dynamic MMR. With
+/// concrete types, see [`MMR::new_typed`], hashing and store calls are type-checked and inlined
+/// at compile time.
 #[derive(Debug)]
-pub struct MMR {
-    pub store: Arc<dyn Store>,
-    pub hasher: Arc<dyn Hasher>,
+pub struct MMR<H: ?Sized = dyn Hasher, S: ?Sized = dyn Store> {
+    pub store: Arc<S>,
+    pub hasher: Arc<H>,
     pub mmr_id: String,
-    pub leaves_count: InStoreCounter,
-    pub elements_count: InStoreCounter,
-    pub hashes: InStoreTable,
-    pub root_hash: InStoreTable,
+    pub leaves_count: InStoreCounter<S>,
+    pub elements_count: InStoreCounter<S>,
+    pub hashes: InStoreTable<S>,
+    pub root_hash: InStoreTable<S>,
+    pub node_hashing: NodeHashing,
+    pub domain_separation: DomainSeparation,
+    pub peak_bagging: PeakBagging,
     #[cfg(feature = "stacked_mmr")]
     pub sub_mmrs: SizesToMMRs,
+    pub(crate) pruning_state: PruningStateCache,
+    pub(crate) hashing_metadata_checked: bool,
+}
+
+/// The MMR over trait objects, which `MMR` defaults to
+pub type DynMMR = MMR<dyn Hasher, dyn Store>;
+
+impl<H: ?Sized, S: ?Sized> Clone for MMR<H, S> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            hasher: self.hasher.clone(),
+            mmr_id: self.mmr_id.clone(),
+            leaves_count: self.leaves_count.clone(),
+            elements_count: self.elements_count.clone(),
+            hashes: self.hashes.clone(),
+            root_hash: self.root_hash.clone(),
+            node_hashing: self.node_hashing,
+            domain_separation: self.domain_separation,
+            peak_bagging: self.peak_bagging,
+            #[cfg(feature = "stacked_mmr")]
+            sub_mmrs: self.sub_mmrs.clone(),
+            pruning_state: self.pruning_state.clone(),
+            hashing_metadata_checked: self.hashing_metadata_checked,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -69,22 +130,27 @@ pub type SizesToMMRs = Vec<(usize, MmrMetadata)>;
 
 impl MMR {
     pub fn new(store: Arc<dyn Store>, hasher: Arc<dyn Hasher>, mmr_id: Option<String>) -> Self {
-        let mmr_id = mmr_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+        MMR::new_typed(store, hasher, mmr_id)
+    }
 
-        let (leaves_count, elements_count, root_hash, hashes) =
-            MMR::get_stores(&mmr_id, store.clone());
+    /// Opens an existing MMR with the node hashing, domain separation and peak bagging recorded by its first append
+    pub async fn open(
+        store: Arc<dyn Store>,
+        hasher: Arc<dyn Hasher>,
+        mmr_id: String,
+    ) -> Result<Self, MMRError> {
+        MMR::open_typed(store, hasher, mmr_id).await
+    }
 
-        Self {
-            leaves_count,
-            elements_count,
-            hashes,
-            root_hash,
-            store,
-            hasher,
-            mmr_id,
-            #[cfg(feature = "stacked_mmr")]
-            sub_mmrs: Vec::new(),
-        }
+    pub fn get_hashing_keys(mmr_id: &str) -> (String, String) {
+        (
+            MMR::encode_store_key(mmr_id, TreeMetadataKeys::NodeHashing, SubKey::None),
+            MMR::encode_store_key(mmr_id, TreeMetadataKeys::DomainSeparation, SubKey::None),
+        )
+    }
+
+    pub fn get_peak_bagging_key(mmr_id: &str) -> String {
+        MMR::encode_store_key(mmr_id, TreeMetadataKeys::PeakBagging, SubKey::None)
     }
 
     pub async fn create_with_genesis(
@@ -102,14 +168,6 @@ impl MMR {
         Ok(mmr)
     }
 
-    pub fn get_metadata(&self) -> MmrMetadata {
-        MmrMetadata {
-            mmr_id: self.mmr_id.clone(),
-            store: self.store.clone(),
-            hasher: self.hasher.get_name(),
-        }
-    }
-
     pub fn get_store_keys(mmr_id: &str) -> (String, String, String, String) {
         (
             format!("{}:{}", mmr_id, TreeMetadataKeys::LeafCount),
@@ -137,14 +195,19 @@ impl MMR {
         let store_key = format!("{}:{}", mmr_id, key);
         match sub_key {
             SubKey::None => store_key,
-            _ => format!("{}:{}", store_key, sub_key.to_string()),
+            _ => format!("{}:{}", store_key, sub_key),
         }
     }
 
-    pub fn get_stores(
+    pub fn get_stores<S: Store + ?Sized>(
         mmr_id: &str,
-        store_rc: Arc<dyn Store>,
-    ) -> (InStoreCounter, InStoreCounter, InStoreTable, InStoreTable) {
+        store_rc: Arc<S>,
+    ) -> (
+        InStoreCounter<S>,
+        InStoreCounter<S>,
+        InStoreTable<S>,
+        InStoreTable<S>,
+    ) {
         let (leaves_count_key, elements_count_key, root_hash_key, hashes_key) =
             MMR::get_store_keys(mmr_id);
 
@@ -156,9 +219,208 @@ impl MMR {
         )
     }
 
+    /// Lists the ids of the MMRs found in a store, sorted
+    ///
+    /// There is no index of the ids, so this scans every key of the store.
+    pub async fn list_ids(store: Arc<dyn Store>) -> Result<Vec<String>, MMRError> {
+        let suffix = format!(":{}", TreeMetadataKeys::ElementCount);
+        let ids: BTreeSet<String> = store
+            .scan_prefix("")
+            .try_filter_map(|(key, _)| {
+                //? Every MMR has an elements count, even right after being pruned
+                let id = key
+                    .strip_suffix(&suffix)
+                    .filter(|id| !id.contains(':'))
+                    .map(str::to_string);
+                async move { Ok(id) }
+            })
+            .try_collect()
+            .await?;
+
+        Ok(ids.into_iter().collect())
+    }
+}
+
+impl<H, S> MMR<H, S>
+where
+    H: Hasher + ?Sized,
+    S: Store + ?Sized,
+{
+    /// Creates an MMR over concrete hasher and store types, see [`MMR::new`] for the dynamic one
+    pub fn new_typed(store: Arc<S>, hasher: Arc<H>, mmr_id: Option<String>) -> Self {
+        let mmr_id = mmr_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        let (leaves_count, elements_count, root_hash, hashes) =
+            MMR::get_stores(&mmr_id, store.clone());
+
+        Self {
+            leaves_count,
+            elements_count,
+            hashes,
+            root_hash,
+            store,
+            hasher,
+            mmr_id,
+            node_hashing: NodeHashing::default(),
+            domain_separation: DomainSeparation::default(),
+            peak_bagging: PeakBagging::default(),
+            #[cfg(feature = "stacked_mmr")]
+            sub_mmrs: Vec::new(),
+            pruning_state: PruningStateCache::default(),
+            hashing_metadata_checked: false,
+        }
+    }
+
+    /// Sets how sibling nodes are combined, the same strategy must be used to verify the proofs
+    pub fn with_node_hashing(mut self, node_hashing: NodeHashing) -> Self {
+        self.node_hashing = node_hashing;
+        self
+    }
+
+    /// Sets whether leaves and nodes are hashed in distinct domains, see [`DomainSeparation`]
+    pub fn with_domain_separation(mut self, domain_separation: DomainSeparation) -> Self {
+        self.domain_separation = domain_separation;
+        self
+    }
+
+    /// Sets how the peaks are bagged and the root derived, see [`PeakBagging`]
+    pub fn with_peak_bagging(mut self, peak_bagging: PeakBagging) -> Self {
+        self.peak_bagging = peak_bagging;
+        self
+    }
+
+    /// Opens an existing MMR over concrete hasher and store types, see [`MMR::open`]
+    pub async fn open_typed(
+        store: Arc<S>,
+        hasher: Arc<H>,
+        mmr_id: String,
+    ) -> Result<Self, MMRError> {
+        let mut mmr = MMR::new_typed(store, hasher, Some(mmr_id));
+        mmr.restore_hashing_metadata().await?;
+        Ok(mmr)
+    }
+
+    /// Applies the node hashing, domain separation and peak bagging recorded for this MMR, if any
+    pub(crate) async fn restore_hashing_metadata(&mut self) -> Result<(), MMRError> {
+        let (node_hashing_key, domain_separation_key) = MMR::get_hashing_keys(&self.mmr_id);
+
+        if let Some(node_hashing) = self.store.get(&node_hashing_key).await? {
+            self.node_hashing = NodeHashing::from_str(&node_hashing)?;
+        }
+        if let Some(domain_separation) = self.store.get(&domain_separation_key).await? {
+            self.domain_separation = DomainSeparation::from_str(&domain_separation)?;
+        }
+        let peak_bagging_key = MMR::get_peak_bagging_key(&self.mmr_id);
+        if let Some(peak_bagging) = self.store.get(&peak_bagging_key).await? {
+            self.peak_bagging = PeakBagging::from_str(&peak_bagging)?;
+        }
+
+        Ok(())
+    }
+
+    /// Records the node hashing, domain separation and peak bagging, so verifiers know which ones to apply
+    pub(crate) async fn record_hashing_metadata(&self) -> Result<(), MMRError> {
+        let (node_hashing_key, domain_separation_key) = MMR::get_hashing_keys(&self.mmr_id);
+        self.store
+            .set_many(HashMap::from([
+                (node_hashing_key, self.node_hashing.to_string()),
+                (domain_separation_key, self.domain_separation.to_string()),
+                (
+                    MMR::get_peak_bagging_key(&self.mmr_id),
+                    self.peak_bagging.to_string(),
+                ),
+            ]))
+            .await?;
+        Ok(())
+    }
+
+    /// Records the hashing metadata if there is none, or checks it against this MMR's settings
+    ///
+    /// Only done once per instance, so an MMR reattached with [`MMR::new`] and other settings
+    /// fails on its first append instead of mixing hashes.
+    pub(crate) async fn ensure_hashing_metadata(&mut self) -> Result<(), MMRError> {
+        if self.hashing_metadata_checked {
+            return Ok(());
+        }
+
+        let (node_hashing_key, domain_separation_key) = MMR::get_hashing_keys(&self.mmr_id);
+        let peak_bagging_key = MMR::get_peak_bagging_key(&self.mmr_id);
+        let recorded = self
+            .store
+            .get_many(vec![
+                &node_hashing_key,
+                &domain_separation_key,
+                &peak_bagging_key,
+            ])
+            .await?;
+
+        if recorded.is_empty() {
+            self.record_hashing_metadata().await?;
+        } else {
+            //? A key recorded before its setting existed was the default
+            let settings = [
+                (
+                    "node hashing",
+                    node_hashing_key,
+                    self.node_hashing.to_string(),
+                    NodeHashing::default().to_string(),
+                ),
+                (
+                    "domain separation",
+                    domain_separation_key,
+                    self.domain_separation.to_string(),
+                    DomainSeparation::default().to_string(),
+                ),
+                (
+                    "peak bagging",
+                    peak_bagging_key,
+                    self.peak_bagging.to_string(),
+                    PeakBagging::default().to_string(),
+                ),
+            ];
+            for (name, key, value, default) in settings {
+                if recorded.get(&key).unwrap_or(&default) != &value {
+                    return Err(MMRError::HashingMetadataMismatch(name.to_string()));
+                }
+            }
+        }
+
+        self.hashing_metadata_checked = true;
+        Ok(())
+    }
+
+    /// The hashing settings of the MMR, as given to the verifiers of its range proofs
+    pub fn hashing_options(&self) -> HashingOptions {
+        HashingOptions {
+            node_hashing: self.node_hashing,
+            domain_separation: self.domain_separation,
+            peak_bagging: self.peak_bagging,
+        }
+    }
+
+    /// Deletes every key of this MMR from its store
+    pub async fn delete(self) -> Result<(), MMRError> {
+        let prefix = format!("{}:", self.mmr_id);
+        let keys: Vec<String> = self
+            .store
+            .scan_prefix(&prefix)
+            .map_ok(|(key, _)| key)
+            .try_collect()
+            .await?;
+
+        self.store
+            .delete_many(keys.iter().map(AsRef::as_ref).collect())
+            .await?;
+        Ok(())
+    }
+
     pub async fn append(&mut self, value: String) -> Result<AppendResult, MMRError> {
         self.hasher.is_element_size_valid(&value)?;
+        let value = self
+            .domain_separation
+            .hash_leaf(self.hasher.as_ref(), value)?;
 
+        self.ensure_hashing_metadata().await?;
         let elements_count = self.elements_count.get().await?;
 
         let mut peaks = self
@@ -190,7 +452,12 @@ impl MMR {
                 None => return Err(MMRError::NoHashFoundForIndex(last_element_idx)),
             };
 
-            let parent_hash = self.hasher.hash(vec![left_hash, right_hash])?;
+            let parent_hash = self.domain_separation.hash_node(
+                self.hasher.as_ref(),
+                self.node_hashing,
+                left_hash,
+                right_hash,
+            )?;
 
             self.hashes
                 .set(&parent_hash, SubKey::Usize(last_element_idx))
@@ -216,6 +483,42 @@ impl MMR {
         })
     }
 
+    /// Appends the hash of raw `data`, see [`Hasher::hash_data`]
+    ///
+    /// With `keep_preimage`, `data` is stored too and can be read back with [`MMR::get_preimage`].
+    pub async fn append_data(
+        &mut self,
+        data: &[u8],
+        keep_preimage: bool,
+    ) -> Result<AppendResult, MMRError> {
+        let result = self.append(self.hasher.hash_data(data)?).await?;
+
+        if keep_preimage {
+            let key = MMR::encode_store_key(
+                &self.mmr_id,
+                TreeMetadataKeys::Preimages,
+                SubKey::Usize(result.element_index),
+            );
+            self.store.set(&key, &hex::encode(data)).await?;
+        }
+
+        Ok(result)
+    }
+
+    /// Returns the data appended with [`MMR::append_data`], if its preimage was kept
+    pub async fn get_preimage(&self, element_index: usize) -> Result<Option<Vec<u8>>, MMRError> {
+        let key = MMR::encode_store_key(
+            &self.mmr_id,
+            TreeMetadataKeys::Preimages,
+            SubKey::Usize(element_index),
+        );
+
+        match self.store.get(&key).await? {
+            Some(preimage) => Ok(Some(hex::decode(preimage).map_err(HasherError::from)?)),
+            None => Ok(None),
+        }
+    }
+
     pub async fn get_proof(
         &self,
         element_index: usize,
@@ -235,6 +538,8 @@ impl MMR {
             return Err(MMRError::InvalidElementIndex);
         }
 
+        self.ensure_provable(&[element_index], tree_size).await?;
+
         let peaks = find_peaks(tree_size);
 
         let siblings = find_siblings(element_index, tree_size)?;
@@ -300,6 +605,8 @@ impl MMR {
             }
         }
 
+        self.ensure_provable(&elements_indexes, tree_size).await?;
+
         let peaks = find_peaks(tree_size);
         let peaks_hashes = self.retrieve_peaks_hashes(peaks, None).await?;
 
@@ -402,19 +709,14 @@ impl MMR {
             return Ok(false);
         }
 
-        let mut hash = element_value.clone();
-        let mut leaf_index = element_index_to_leaf_index(element_index)?;
-
-        for proof_hash in proof.siblings_hashes.iter() {
-            let is_right = leaf_index % 2 == 1;
-            leaf_index /= 2;
-
-            hash = self.hasher.hash(if is_right {
-                vec![proof_hash.clone(), hash.clone()]
-            } else {
-                vec![hash.clone(), proof_hash.clone()]
-            })?;
-        }
+        let hash = compute_peak_hash(
+            self.hasher.as_ref(),
+            self.node_hashing,
+            self.domain_separation,
+            element_index,
+            element_value,
+            &proof.siblings_hashes,
+        )?;
 
         let peak_hashes = self
             .retrieve_peaks_hashes(find_peaks(tree_size), None)
@@ -423,6 +725,17 @@ impl MMR {
         Ok(peak_hashes[peak_index] == hash)
     }
 
+    /// Verifies a proof of an element appended with [`MMR::append_data`]
+    pub async fn verify_proof_data(
+        &self,
+        proof: Proof,
+        data: &[u8],
+        options: Option<ProofOptions>,
+    ) -> Result<bool, MMRError> {
+        let element_value = self.hasher.hash_data(data)?;
+        self.verify_proof(proof, element_value, options).await
+    }
+
     pub async fn get_peaks(&self, option: PeaksOptions) -> Result<Vec<String>, MMRError> {
         let tree_size = match option.elements_count {
             Some(count) => count,
@@ -431,8 +744,8 @@ impl MMR {
 
         let peaks_idxs = find_peaks(tree_size);
         let peaks = self.retrieve_peaks_hashes(peaks_idxs, None).await?;
-        if (option.formatting_opts).is_some() {
-            match format_peaks(peaks, &option.formatting_opts.unwrap()) {
+        if let Some(formatting_opts) = option.formatting_opts {
+            match format_peaks(peaks, &formatting_opts) {
                 Ok(peaks) => Ok(peaks),
                 Err(e) => Err(MMRError::Formatting(e)),
             }
@@ -472,23 +785,14 @@ impl MMR {
         };
         let peaks_idxs = find_peaks(tree_size);
 
-        let peaks_hashes = self.retrieve_peaks_hashes(peaks_idxs.clone(), None).await?;
-
-        match peaks_idxs.len() {
-            // Use original peaks_idxs here
-            0 => Ok("0x0".to_string()),
-            1 => Ok(peaks_hashes[0].clone()),
-            _ => {
-                let mut peaks_hashes: VecDeque<String> = peaks_hashes.into();
-                let last = peaks_hashes.pop_back().unwrap();
-                let second_last = peaks_hashes.pop_back().unwrap();
-                let root0 = self.hasher.hash(vec![second_last, last])?;
-
-                Ok(peaks_hashes.into_iter().rev().fold(root0, |prev, cur| {
-                    self.hasher.hash(vec![cur, prev]).unwrap()
-                }))
-            }
-        }
+        let peaks_hashes = self.retrieve_peaks_hashes(peaks_idxs, None).await?;
+
+        self.bag_peaks_hashes(peaks_hashes)
+    }
+
+    /// Bags the given peaks hashes without touching the store
+    pub fn bag_peaks_hashes(&self, peaks_hashes: Vec<String>) -> Result<String, MMRError> {
+        self.peak_bagging.bag(self.hasher.as_ref(), peaks_hashes)
     }
 
     pub fn calculate_root_hash(
@@ -496,12 +800,66 @@ impl MMR {
         bag: &str,
         elements_count: usize,
     ) -> Result<String, MMRError> {
-        match self
-            .hasher
-            .hash(vec![elements_count.to_string(), bag.to_string()])
-        {
-            Ok(root_hash) => Ok(root_hash),
-            Err(e) => Err(MMRError::Hasher(e)),
+        self.peak_bagging
+            .root(self.hasher.as_ref(), bag, elements_count)
+    }
+}
+
+impl<H, S> MMR<H, S>
+where
+    H: Hasher + ?Sized,
+    S: IntoDynStore + ?Sized,
+{
+    pub fn get_metadata(&self) -> MmrMetadata {
+        MmrMetadata {
+            mmr_id: self.mmr_id.clone(),
+            store: self.store.clone().into_dyn_store(),
+            hasher: self.hasher.get_name(),
+        }
+    }
+}
+
+impl<H, S> MMR<H, S>
+where
+    H: Hasher + 'static,
+    S: IntoDynStore + ?Sized,
+{
+    /// Turns a typed MMR into the dynamic one
+    pub fn into_dyn(self) -> MMR {
+        let store = self.store.into_dyn_store();
+        #[allow(unused_mut)]
+        let (leaves_count, elements_count, root_hash, mut hashes) =
+            MMR::get_stores(&self.mmr_id, store.clone());
+
+        //? A stacked MMR keeps reading from its sub MMRs
+        #[cfg(feature = "stacked_mmr")]
+        if let Some(sub_mmrs) = self.hashes.sub_mmrs {
+            let sub_mmrs = sub_mmrs
+                .into_iter()
+                .map(|sub_mmr| SubMMR {
+                    size: sub_mmr.size,
+                    key: sub_mmr.key,
+                    store: sub_mmr.store.into_dyn_store(),
+                })
+                .collect();
+            MMR::route_to_sub_mmrs(&mut hashes, sub_mmrs);
+        }
+
+        MMR {
+            store,
+            hasher: self.hasher,
+            mmr_id: self.mmr_id,
+            leaves_count,
+            elements_count,
+            hashes,
+            root_hash,
+            node_hashing: self.node_hashing,
+            domain_separation: self.domain_separation,
+            peak_bagging: self.peak_bagging,
+            #[cfg(feature = "stacked_mmr")]
+            sub_mmrs: self.sub_mmrs,
+            pruning_state: self.pruning_state,
+            hashing_metadata_checked: self.hashing_metadata_checked,
         }
     }
 }
@@ -1,4 +1,11 @@
+use num_bigint::BigUint;
+use num_traits::Num;
+use starknet::core::types::FieldElement;
 use thiserror::Error;
+
+use super::Proof;
+use crate::hasher::HashingFunction;
+
 /// Formatting
 #[derive(Clone)]
 pub struct FormattingOptions {
@@ -28,6 +35,33 @@ pub enum FormattingError {
     PeaksOutputSizeError,
     #[error("Formatting: Expected proof output size is smaller than the actual size")]
     ProofOutputSizeError,
+    #[error("Formatting: {0} does not fit the Cairo encoding")]
+    CairoValueError(String),
+    #[error("Formatting: {encoding:?} is not the Cairo encoding of {hashing_function} hashes")]
+    CairoEncodingMismatch {
+        hashing_function: HashingFunction,
+        encoding: CairoEncoding,
+    },
+}
+
+/// How each hash is laid out in a Cairo felt array
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CairoEncoding {
+    /// One `felt252` per hash, for Poseidon and Pedersen MMRs
+    #[default]
+    Felt,
+    /// A `u256` per hash, serialized as its low then high 128 bits, for Keccak MMRs
+    Uint256,
+}
+
+impl CairoEncoding {
+    /// The encoding cairo-lib expects for the hashes of `hashing_function`
+    pub fn for_hashing_function(hashing_function: HashingFunction) -> Self {
+        match hashing_function {
+            HashingFunction::Keccak256 => CairoEncoding::Uint256,
+            HashingFunction::Poseidon | HashingFunction::Pedersen => CairoEncoding::Felt,
+        }
+    }
 }
 
 pub fn format_peaks(
@@ -64,3 +98,67 @@ pub fn format_proof(
 
     Ok(result)
 }
+
+/// Formats a proof as the calldata of the cairo-lib MMR `verify_proof`
+///
+/// The layout is `[index, hash, peaks_len, ...peaks, siblings_len, ...siblings]`, where the
+/// lengths count hashes, not felts. Every felt is a `0x` prefixed hex string. `encoding` must
+/// be the one of `hashing_function`, the hasher of the MMR the proof comes from.
+pub fn format_proof_cairo(
+    proof: &Proof,
+    hashing_function: HashingFunction,
+    encoding: CairoEncoding,
+) -> Result<Vec<String>, FormattingError> {
+    if encoding != CairoEncoding::for_hashing_function(hashing_function) {
+        return Err(FormattingError::CairoEncodingMismatch {
+            hashing_function,
+            encoding,
+        });
+    }
+
+    let mut felts = vec![format!("{:#x}", proof.element_index)];
+    encode_cairo_value(&mut felts, &proof.element_hash, encoding)?;
+    for hashes in [&proof.peaks_hashes, &proof.siblings_hashes] {
+        felts.push(format!("{:#x}", hashes.len()));
+        for hash in hashes {
+            encode_cairo_value(&mut felts, hash, encoding)?;
+        }
+    }
+
+    Ok(felts)
+}
+
+/// Appends a hash, given in hex or decimal, to a felt array
+fn encode_cairo_value(
+    felts: &mut Vec<String>,
+    value: &str,
+    encoding: CairoEncoding,
+) -> Result<(), FormattingError> {
+    let invalid = || FormattingError::CairoValueError(value.to_string());
+    let number = match value.strip_prefix("0x") {
+        Some(hex) => BigUint::from_str_radix(hex, 16),
+        None => BigUint::from_str_radix(value, 10),
+    }
+    .map_err(|_| invalid())?;
+    if number.bits() > 256 {
+        return Err(invalid());
+    }
+
+    match encoding {
+        CairoEncoding::Felt => {
+            let mut bytes = [0u8; 32];
+            let be_bytes = number.to_bytes_be();
+            bytes[32 - be_bytes.len()..].copy_from_slice(&be_bytes);
+            //? Fails when the value is not below the field prime
+            let felt = FieldElement::from_bytes_be(&bytes).map_err(|_| invalid())?;
+            felts.push(format!("{:#x}", felt));
+        }
+        CairoEncoding::Uint256 => {
+            let low = &number & BigUint::from(u128::MAX);
+            let high = &number >> 128;
+            felts.push(format!("{:#x}", low));
+            felts.push(format!("{:#x}", high));
+        }
+    }
+    Ok(())
+}
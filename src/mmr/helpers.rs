@@ -1,9 +1,12 @@
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::str::FromStr;
 use thiserror::Error;
 
+use crate::hasher::{DomainSeparation, Hasher, NodeHashing};
+
 use super::formatting::{PeaksFormattingOptions, ProofFormattingOptions};
 use super::MMRError;
 
@@ -30,6 +33,87 @@ pub struct Proof {
     pub elements_count: usize,
 }
 
+impl Proof {
+    /// Returns the indexes of the elements appended after this proof that are needed to update it
+    pub fn required_nodes_for_update(
+        &self,
+        new_elements_count: usize,
+    ) -> Result<Vec<usize>, MMRError> {
+        self.validate_update(new_elements_count)?;
+
+        let mut required = find_siblings(self.element_index, new_elements_count)?;
+        required.extend(find_peaks(new_elements_count));
+        required.retain(|&idx| idx > self.elements_count);
+        Ok(array_deduplicate(required))
+    }
+
+    /// Updates the proof to a bigger MMR without touching the store
+    ///
+    /// `new_nodes` maps element indexes appended after this proof to their hashes. It must at
+    /// least contain the ones returned by [`Proof::required_nodes_for_update`]. The hashes of the
+    /// older nodes come from the peaks of this proof.
+    pub fn update(
+        &self,
+        new_elements_count: usize,
+        new_nodes: &HashMap<usize, String>,
+    ) -> Result<Proof, MMRError> {
+        self.validate_update(new_elements_count)?;
+
+        let old_peaks: HashMap<usize, &String> = find_peaks(self.elements_count)
+            .into_iter()
+            .zip(self.peaks_hashes.iter())
+            .collect();
+        let get_hash = |idx: usize| {
+            let hash = if idx <= self.elements_count {
+                old_peaks.get(&idx).copied()
+            } else {
+                new_nodes.get(&idx)
+            };
+            hash.cloned().ok_or(MMRError::NoHashFoundForIndex(idx))
+        };
+
+        //? Subtrees never change, so the old siblings are the first siblings of the new proof
+        let siblings = find_siblings(self.element_index, new_elements_count)?;
+        let mut siblings_hashes = self.siblings_hashes.clone();
+        for &idx in siblings.iter().skip(self.siblings_hashes.len()) {
+            siblings_hashes.push(get_hash(idx)?);
+        }
+
+        let peaks_hashes = find_peaks(new_elements_count)
+            .into_iter()
+            .map(get_hash)
+            .collect::<Result<Vec<String>, MMRError>>()?;
+
+        Ok(Proof {
+            element_index: self.element_index,
+            element_hash: self.element_hash.clone(),
+            siblings_hashes,
+            peaks_hashes,
+            elements_count: new_elements_count,
+        })
+    }
+
+    fn validate_update(&self, new_elements_count: usize) -> Result<(), MMRError> {
+        if new_elements_count < self.elements_count {
+            return Err(MMRError::InvalidElementCount);
+        }
+        elements_count_to_leaf_count(new_elements_count)?;
+
+        if self.element_index == 0 || self.element_index > self.elements_count {
+            return Err(MMRError::InvalidElementIndex);
+        }
+        if find_peaks(self.elements_count).len() != self.peaks_hashes.len() {
+            return Err(MMRError::InvalidPeaksCount);
+        }
+        let (_, peak_height) = get_peak_info(self.elements_count, self.element_index);
+        if self.siblings_hashes.len() != peak_height {
+            return Err(MMRError::InvalidElementIndex);
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct ProofOptions {
     pub elements_count: Option<usize>,
@@ -41,6 +125,17 @@ pub struct PeaksOptions {
     pub formatting_opts: Option<PeaksFormattingOptions>,
 }
 
+/// How an MMR hashes its nodes and derives its root, as trusted by a verifier
+///
+/// It must come from the verifier, see [`MMR::hashing_options`](super::MMR::hashing_options),
+/// never from the proof being checked.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HashingOptions {
+    pub node_hashing: NodeHashing,
+    pub domain_separation: DomainSeparation,
+    pub peak_bagging: PeakBagging,
+}
+
 #[derive(Clone)]
 pub struct FormattingOptionsBundle {
     pub proof: ProofFormattingOptions,
@@ -60,6 +155,13 @@ pub enum TreeMetadataKeys {
     ElementCount,
     RootHash,
     Hashes,
+    Shards,
+    PrunedElementsCount,
+    PinnedLeaves,
+    NodeHashing,
+    DomainSeparation,
+    Preimages,
+    PeakBagging,
 }
 
 impl FromStr for TreeMetadataKeys {
@@ -69,6 +171,13 @@ impl FromStr for TreeMetadataKeys {
             "elements_count" => Ok(TreeMetadataKeys::ElementCount),
             "root_hash" => Ok(TreeMetadataKeys::RootHash),
             "hashes" => Ok(TreeMetadataKeys::Hashes),
+            "shards" => Ok(TreeMetadataKeys::Shards),
+            "pruned_elements_count" => Ok(TreeMetadataKeys::PrunedElementsCount),
+            "pinned_leaves" => Ok(TreeMetadataKeys::PinnedLeaves),
+            "node_hashing" => Ok(TreeMetadataKeys::NodeHashing),
+            "domain_separation" => Ok(TreeMetadataKeys::DomainSeparation),
+            "preimages" => Ok(TreeMetadataKeys::Preimages),
+            "peak_bagging" => Ok(TreeMetadataKeys::PeakBagging),
             _ => Err(TreeMetadataKeysError::InvalidKey),
         }
     }
@@ -83,6 +192,13 @@ impl Display for TreeMetadataKeys {
             TreeMetadataKeys::ElementCount => write!(f, "elements_count"),
             TreeMetadataKeys::RootHash => write!(f, "root_hash"),
             TreeMetadataKeys::Hashes => write!(f, "hashes"),
+            TreeMetadataKeys::Shards => write!(f, "shards"),
+            TreeMetadataKeys::PrunedElementsCount => write!(f, "pruned_elements_count"),
+            TreeMetadataKeys::PinnedLeaves => write!(f, "pinned_leaves"),
+            TreeMetadataKeys::NodeHashing => write!(f, "node_hashing"),
+            TreeMetadataKeys::DomainSeparation => write!(f, "domain_separation"),
+            TreeMetadataKeys::Preimages => write!(f, "preimages"),
+            TreeMetadataKeys::PeakBagging => write!(f, "peak_bagging"),
         }
     }
 }
@@ -250,3 +366,177 @@ pub fn mmr_size_to_leaf_count(mmr_size: usize) -> usize {
 
     leaf_count
 }
+
+/// How the peaks are bagged into a single hash, and how the root is derived from it
+///
+/// Whatever the policy, a single peak is its own bag and an MMR without peaks bags to `0x0`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PeakBagging {
+    /// Folds the peaks from right to left as `hash(peak, bag)`, the root is `hash(elements_count, bag)`
+    #[default]
+    RightToLeft,
+    /// Folds the peaks from left to right as `hash(bag, peak)`, the root is `hash(elements_count, bag)`
+    LeftToRight,
+    /// Folds the peaks from right to left as `hash(bag, peak)`, the root is the bag itself,
+    /// like ckb-merkle-mountain-range and Polkadot's MMR pallet
+    RightToLeftWithoutSize,
+    /// Hashes all the peaks at once as `hash(peaks)`, the root is `hash(elements_count, bag)`,
+    /// for verifiers that are given the peaks directly
+    Flat,
+}
+
+impl PeakBagging {
+    /// Bags the peaks hashes
+    pub fn bag<H: Hasher + ?Sized>(
+        &self,
+        hasher: &H,
+        peaks_hashes: Vec<String>,
+    ) -> Result<String, MMRError> {
+        if peaks_hashes.len() < 2 {
+            return Ok(peaks_hashes
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| "0x0".to_string()));
+        }
+
+        match self {
+            PeakBagging::RightToLeft => bag_peaks(hasher, peaks_hashes),
+            PeakBagging::LeftToRight => {
+                let mut peaks_hashes = peaks_hashes.into_iter();
+                let first = peaks_hashes.next().unwrap();
+                Ok(peaks_hashes.try_fold(first, |bag, peak| hasher.hash(vec![bag, peak]))?)
+            }
+            PeakBagging::RightToLeftWithoutSize => {
+                let mut peaks_hashes = peaks_hashes.into_iter().rev();
+                let last = peaks_hashes.next().unwrap();
+                Ok(peaks_hashes.try_fold(last, |bag, peak| hasher.hash(vec![bag, peak]))?)
+            }
+            PeakBagging::Flat => Ok(hasher.hash(peaks_hashes)?),
+        }
+    }
+
+    /// Derives the root from the bag of the peaks of an MMR of `elements_count` elements
+    pub fn root<H: Hasher + ?Sized>(
+        &self,
+        hasher: &H,
+        bag: &str,
+        elements_count: usize,
+    ) -> Result<String, MMRError> {
+        match self {
+            PeakBagging::RightToLeftWithoutSize => Ok(bag.to_string()),
+            _ => calculate_root(hasher, bag, elements_count),
+        }
+    }
+}
+
+impl FromStr for PeakBagging {
+    type Err = MMRError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "right_to_left" => Ok(PeakBagging::RightToLeft),
+            "left_to_right" => Ok(PeakBagging::LeftToRight),
+            "right_to_left_without_size" => Ok(PeakBagging::RightToLeftWithoutSize),
+            "flat" => Ok(PeakBagging::Flat),
+            _ => Err(MMRError::InvalidPeakBagging),
+        }
+    }
+}
+
+impl Display for PeakBagging {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeakBagging::RightToLeft => write!(f, "right_to_left"),
+            PeakBagging::LeftToRight => write!(f, "left_to_right"),
+            PeakBagging::RightToLeftWithoutSize => write!(f, "right_to_left_without_size"),
+            PeakBagging::Flat => write!(f, "flat"),
+        }
+    }
+}
+
+/// Bags the peaks hashes from right to left
+pub fn bag_peaks<H: Hasher + ?Sized>(
+    hasher: &H,
+    peaks_hashes: Vec<String>,
+) -> Result<String, MMRError> {
+    match peaks_hashes.len() {
+        0 => Ok("0x0".to_string()),
+        1 => Ok(peaks_hashes[0].clone()),
+        _ => {
+            let mut peaks_hashes: VecDeque<String> = peaks_hashes.into();
+            let last = peaks_hashes.pop_back().unwrap();
+            let second_last = peaks_hashes.pop_back().unwrap();
+            let root0 = hasher.hash(vec![second_last, last])?;
+
+            Ok(peaks_hashes
+                .into_iter()
+                .rev()
+                .try_fold(root0, |prev, cur| hasher.hash(vec![cur, prev]))?)
+        }
+    }
+}
+
+/// Commits the bag of the peaks to the size of the tree
+pub fn calculate_root<H: Hasher + ?Sized>(
+    hasher: &H,
+    bag: &str,
+    elements_count: usize,
+) -> Result<String, MMRError> {
+    Ok(hasher.hash(vec![elements_count.to_string(), bag.to_string()])?)
+}
+
+/// Appends a leaf to the given peaks, merging them like [`super::MMR::append`] does
+///
+/// `value` is the hash stored for the leaf. Returns the hashes of the new elements,
+/// starting with the leaf itself
+pub fn append_to_peaks<H: Hasher + ?Sized>(
+    hasher: &H,
+    node_hashing: NodeHashing,
+    domain_separation: DomainSeparation,
+    peaks: &mut Vec<String>,
+    leaves_count: usize,
+    value: String,
+) -> Result<Vec<String>, MMRError> {
+    let mut new_elements = vec![value.clone()];
+    peaks.push(value);
+
+    for _ in 0..leaf_count_to_append_no_merges(leaves_count) {
+        let (right_hash, left_hash) = match (peaks.pop(), peaks.pop()) {
+            (Some(right_hash), Some(left_hash)) => (right_hash, left_hash),
+            _ => return Err(MMRError::InvalidPeaksCount),
+        };
+
+        let parent_hash =
+            domain_separation.hash_node(hasher, node_hashing, left_hash, right_hash)?;
+        new_elements.push(parent_hash.clone());
+        peaks.push(parent_hash);
+    }
+
+    Ok(new_elements)
+}
+
+/// Hashes a leaf value up to the peak of its mountain using the siblings of its proof
+pub fn compute_peak_hash<H: Hasher + ?Sized>(
+    hasher: &H,
+    node_hashing: NodeHashing,
+    domain_separation: DomainSeparation,
+    element_index: usize,
+    element_value: String,
+    siblings_hashes: &[String],
+) -> Result<String, MMRError> {
+    let mut hash = domain_separation.hash_leaf(hasher, element_value)?;
+    let mut leaf_index = element_index_to_leaf_index(element_index)?;
+
+    for proof_hash in siblings_hashes.iter() {
+        let is_right = leaf_index % 2 == 1;
+        leaf_index /= 2;
+
+        hash = if is_right {
+            domain_separation.hash_node(hasher, node_hashing, proof_hash.clone(), hash)?
+        } else {
+            domain_separation.hash_node(hasher, node_hashing, hash, proof_hash.clone())?
+        };
+    }
+
+    Ok(hash)
+}
@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    hasher::{DomainSeparation, Hasher, NodeHashing},
+    store::{Store, SubKey},
+};
+
+use super::{
+    core::{MMRError, MMR},
+    helpers::{
+        elements_count_to_leaf_count, find_peaks, get_peak_info, map_leaf_index_to_element_index,
+        HashingOptions,
+    },
+};
+
+/// A proof of a contiguous run of leaves
+///
+/// It only carries the hashes bordering the run and the peaks: every node above the leaves is
+/// rebuilt by the verifier from the leaf values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeafRangeProof {
+    /// The leaf index (0-based) of the first proven leaf
+    pub first_leaf_index: usize,
+    pub leaves_count: usize,
+    /// The hashes of the siblings that cannot be computed from the leaves, by element index
+    pub border_hashes: BTreeMap<usize, String>,
+    pub peaks_hashes: Vec<String>,
+    pub elements_count: usize,
+    pub node_hashing: NodeHashing,
+    pub domain_separation: DomainSeparation,
+}
+
+impl LeafRangeProof {
+    /// Rebuilds the peaks covering `leaf_values` and checks them against the trusted `root_hash`
+    /// and `options`, without access to the MMR
+    ///
+    /// A proof claiming other hashing settings than `options` is rejected.
+    pub fn verify(
+        &self,
+        hasher: &dyn Hasher,
+        leaf_values: &[String],
+        root_hash: &str,
+        options: &HashingOptions,
+    ) -> Result<bool, MMRError> {
+        let peaks = find_peaks(self.elements_count);
+        let total_leaves = elements_count_to_leaf_count(self.elements_count)?;
+        if self.node_hashing != options.node_hashing
+            || self.domain_separation != options.domain_separation
+            || leaf_values.len() != self.leaves_count
+            || self.leaves_count == 0
+            || self
+                .first_leaf_index
+                .checked_add(self.leaves_count)
+                .is_none_or(|end| end > total_leaves)
+            || peaks.len() != self.peaks_hashes.len()
+        {
+            return Ok(false);
+        }
+
+        //? Nodes of the current height, by element index: (position in the level, hash, peak height)
+        let mut level = BTreeMap::new();
+        for (offset, value) in leaf_values.iter().enumerate() {
+            let leaf_index = self.first_leaf_index + offset;
+            let element_index = map_leaf_index_to_element_index(leaf_index);
+            let (_, peak_height) = get_peak_info(self.elements_count, element_index);
+            let hash = options.domain_separation.hash_leaf(hasher, value.clone())?;
+            level.insert(element_index, (leaf_index, hash, peak_height));
+        }
+
+        let mut height = 0;
+        while !level.is_empty() {
+            let mut next_level = BTreeMap::new();
+            for (&element_index, (position, hash, peak_height)) in &level {
+                if height == *peak_height {
+                    let peak_index = peaks.iter().position(|&peak| peak == element_index);
+                    if peak_index.map(|i| &self.peaks_hashes[i]) != Some(hash) {
+                        return Ok(false);
+                    }
+                    continue;
+                }
+
+                let (sibling, parent) = sibling_and_parent(element_index, *position, height);
+                let sibling_hash = match level.get(&sibling) {
+                    Some((_, sibling_hash, _)) => sibling_hash,
+                    None => match self.border_hashes.get(&sibling) {
+                        Some(sibling_hash) => sibling_hash,
+                        None => return Ok(false),
+                    },
+                };
+                let (left, right) = if position % 2 == 1 {
+                    (sibling_hash.clone(), hash.clone())
+                } else {
+                    (hash.clone(), sibling_hash.clone())
+                };
+                let parent_hash = options.domain_separation.hash_node(
+                    hasher,
+                    options.node_hashing,
+                    left,
+                    right,
+                )?;
+                next_level.insert(parent, (position / 2, parent_hash, *peak_height));
+            }
+            level = next_level;
+            height += 1;
+        }
+
+        let bag = options
+            .peak_bagging
+            .bag(hasher, self.peaks_hashes.clone())?;
+        Ok(options
+            .peak_bagging
+            .root(hasher, &bag, self.elements_count)?
+            == root_hash)
+    }
+}
+
+impl<H, S> MMR<H, S>
+where
+    H: Hasher + ?Sized,
+    S: Store + ?Sized,
+{
+    /// Proves the leaves from `first_leaf_index` to `last_leaf_index` included (0-based leaf indexes)
+    pub async fn get_leaf_range_proof(
+        &self,
+        first_leaf_index: usize,
+        last_leaf_index: usize,
+        elements_count: Option<usize>,
+    ) -> Result<LeafRangeProof, MMRError> {
+        let tree_size = match elements_count {
+            Some(count) => count,
+            None => self.elements_count.get().await?,
+        };
+        if first_leaf_index > last_leaf_index
+            || last_leaf_index >= elements_count_to_leaf_count(tree_size)?
+        {
+            return Err(MMRError::InvalidElementIndex);
+        }
+
+        let leaves: Vec<usize> = (first_leaf_index..=last_leaf_index)
+            .map(map_leaf_index_to_element_index)
+            .collect();
+        self.ensure_provable(&leaves, tree_size).await?;
+
+        //? Siblings along the paths of the leaves that are not on one of those paths themselves
+        let mut path = HashSet::new();
+        let mut siblings = HashSet::new();
+        for (leaf_index, &element_index) in (first_leaf_index..).zip(&leaves) {
+            let (_, peak_height) = get_peak_info(tree_size, element_index);
+            let (mut current, mut position) = (element_index, leaf_index);
+            path.insert(current);
+            for height in 0..peak_height {
+                let (sibling, parent) = sibling_and_parent(current, position, height);
+                siblings.insert(sibling);
+                if !path.insert(parent) {
+                    break;
+                }
+                current = parent;
+                position /= 2;
+            }
+        }
+        let border_indexes: Vec<usize> = siblings.difference(&path).copied().collect();
+
+        let border_hashes = self
+            .hashes
+            .get_many(border_indexes.iter().copied().map(SubKey::Usize).collect())
+            .await?;
+        let border_hashes = border_indexes
+            .into_iter()
+            .map(|element_index| {
+                border_hashes
+                    .get(&element_index.to_string())
+                    .cloned()
+                    .map(|hash| (element_index, hash))
+                    .ok_or(MMRError::NoHashFoundForIndex(element_index))
+            })
+            .collect::<Result<BTreeMap<usize, String>, MMRError>>()?;
+
+        Ok(LeafRangeProof {
+            first_leaf_index,
+            leaves_count: leaves.len(),
+            border_hashes,
+            peaks_hashes: self
+                .retrieve_peaks_hashes(find_peaks(tree_size), None)
+                .await?,
+            elements_count: tree_size,
+            node_hashing: self.node_hashing,
+            domain_separation: self.domain_separation,
+        })
+    }
+}
+
+/// The sibling and parent element indexes of the node at `element_index`, which is the
+/// `position`-th node of its height in the MMR
+fn sibling_and_parent(element_index: usize, position: usize, height: usize) -> (usize, usize) {
+    let subtree_size = (2 << height) - 1;
+    if position % 2 == 1 {
+        (element_index - subtree_size, element_index + 1)
+    } else {
+        (
+            element_index + subtree_size,
+            element_index + subtree_size + 1,
+        )
+    }
+}
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use crate::{
+    hasher::{DomainSeparation, Hasher, NodeHashing},
+    mmr::{
+        append_to_peaks, compute_peak_hash, elements_count_to_leaf_count, find_peaks,
+        get_peak_info, AppendResult, MMRError, PeakBagging, PeaksOptions, Proof, MMR,
+    },
+    store::Store,
+};
+
+/// An MMR that only keeps its peaks and counters in memory
+///
+/// Appends and roots match [`MMR::append`] and [`MMR::calculate_root_hash`], so it can mirror a
+/// full MMR and verify the proofs it produces, but it cannot produce proofs itself.
+#[derive(Debug)]
+pub struct CompactMMR<H: ?Sized = dyn Hasher> {
+    pub hasher: Arc<H>,
+    pub node_hashing: NodeHashing,
+    pub domain_separation: DomainSeparation,
+    pub peak_bagging: PeakBagging,
+    pub leaves_count: usize,
+    pub elements_count: usize,
+    pub peaks: Vec<String>,
+}
+
+impl<H: ?Sized> Clone for CompactMMR<H> {
+    fn clone(&self) -> Self {
+        Self {
+            hasher: self.hasher.clone(),
+            node_hashing: self.node_hashing,
+            domain_separation: self.domain_separation,
+            peak_bagging: self.peak_bagging,
+            leaves_count: self.leaves_count,
+            elements_count: self.elements_count,
+            peaks: self.peaks.clone(),
+        }
+    }
+}
+
+impl CompactMMR {
+    pub fn new(hasher: Arc<dyn Hasher>) -> Self {
+        CompactMMR::new_typed(hasher)
+    }
+
+    /// Creates a compact MMR from the peaks of a full MMR of size `elements_count`
+    pub fn from_peaks(
+        hasher: Arc<dyn Hasher>,
+        peaks: Vec<String>,
+        elements_count: usize,
+    ) -> Result<Self, MMRError> {
+        CompactMMR::from_peaks_typed(hasher, peaks, elements_count)
+    }
+}
+
+impl<H> CompactMMR<H>
+where
+    H: Hasher + ?Sized,
+{
+    /// Creates a compact MMR over a concrete hasher type, see [`CompactMMR::new`]
+    pub fn new_typed(hasher: Arc<H>) -> Self {
+        Self {
+            hasher,
+            node_hashing: NodeHashing::default(),
+            domain_separation: DomainSeparation::default(),
+            peak_bagging: PeakBagging::default(),
+            leaves_count: 0,
+            elements_count: 0,
+            peaks: Vec::new(),
+        }
+    }
+
+    /// Creates a compact MMR over a concrete hasher type, see [`CompactMMR::from_peaks`]
+    pub fn from_peaks_typed(
+        hasher: Arc<H>,
+        peaks: Vec<String>,
+        elements_count: usize,
+    ) -> Result<Self, MMRError> {
+        let leaves_count = elements_count_to_leaf_count(elements_count)?;
+        if find_peaks(elements_count).len() != peaks.len() {
+            return Err(MMRError::InvalidPeaksCount);
+        }
+
+        Ok(Self {
+            hasher,
+            node_hashing: NodeHashing::default(),
+            domain_separation: DomainSeparation::default(),
+            peak_bagging: PeakBagging::default(),
+            leaves_count,
+            elements_count,
+            peaks,
+        })
+    }
+
+    /// Creates a compact MMR mirroring the current state of `mmr`
+    pub async fn from_mmr<S: Store + ?Sized>(mmr: &MMR<H, S>) -> Result<Self, MMRError> {
+        let elements_count = mmr.elements_count.get().await?;
+        let peaks = mmr
+            .get_peaks(PeaksOptions {
+                elements_count: Some(elements_count),
+                formatting_opts: None,
+            })
+            .await?;
+
+        Ok(
+            CompactMMR::from_peaks_typed(mmr.hasher.clone(), peaks, elements_count)?
+                .with_node_hashing(mmr.node_hashing)
+                .with_domain_separation(mmr.domain_separation)
+                .with_peak_bagging(mmr.peak_bagging),
+        )
+    }
+
+    /// Sets how sibling nodes are combined, see [`MMR::with_node_hashing`]
+    pub fn with_node_hashing(mut self, node_hashing: NodeHashing) -> Self {
+        self.node_hashing = node_hashing;
+        self
+    }
+
+    /// Sets the domain separation, see [`MMR::with_domain_separation`]
+    pub fn with_domain_separation(mut self, domain_separation: DomainSeparation) -> Self {
+        self.domain_separation = domain_separation;
+        self
+    }
+
+    /// Sets how the peaks are bagged, see [`MMR::with_peak_bagging`]
+    pub fn with_peak_bagging(mut self, peak_bagging: PeakBagging) -> Self {
+        self.peak_bagging = peak_bagging;
+        self
+    }
+
+    pub fn append(&mut self, value: String) -> Result<AppendResult, MMRError> {
+        self.hasher.is_element_size_valid(&value)?;
+        let value = self
+            .domain_separation
+            .hash_leaf(self.hasher.as_ref(), value)?;
+
+        let element_index = self.elements_count + 1;
+        let new_elements = append_to_peaks(
+            self.hasher.as_ref(),
+            self.node_hashing,
+            self.domain_separation,
+            &mut self.peaks,
+            self.leaves_count,
+            value,
+        )?;
+
+        self.elements_count += new_elements.len();
+        self.leaves_count += 1;
+
+        Ok(AppendResult {
+            leaves_count: self.leaves_count,
+            elements_count: self.elements_count,
+            element_index,
+            root_hash: self.root_hash()?,
+        })
+    }
+
+    pub fn bag_the_peaks(&self) -> Result<String, MMRError> {
+        self.peak_bagging
+            .bag(self.hasher.as_ref(), self.peaks.clone())
+    }
+
+    pub fn root_hash(&self) -> Result<String, MMRError> {
+        self.peak_bagging.root(
+            self.hasher.as_ref(),
+            &self.bag_the_peaks()?,
+            self.elements_count,
+        )
+    }
+
+    /// Verifies a proof produced by a full MMR of the same size
+    pub fn verify_proof(&self, proof: &Proof, element_value: String) -> Result<bool, MMRError> {
+        if proof.elements_count != self.elements_count {
+            return Err(MMRError::InvalidElementCount);
+        }
+
+        let element_index = proof.element_index;
+        if element_index == 0 || element_index > self.elements_count {
+            return Err(MMRError::InvalidElementIndex);
+        }
+
+        let (peak_index, peak_height) = get_peak_info(self.elements_count, element_index);
+        if proof.siblings_hashes.len() != peak_height {
+            return Ok(false);
+        }
+
+        let hash = compute_peak_hash(
+            self.hasher.as_ref(),
+            self.node_hashing,
+            self.domain_separation,
+            element_index,
+            element_value,
+            &proof.siblings_hashes,
+        )?;
+
+        Ok(self.peaks[peak_index] == hash)
+    }
+}
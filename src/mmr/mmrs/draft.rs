@@ -1,11 +1,22 @@
 use crate::{
-    mmr::{MMRError, MMR},
-    store::memory::InMemoryStore,
+    hasher::Hasher,
+    mmr::{
+        append_to_peaks, find_peaks, map_leaf_index_to_element_index, MMRError, PeaksOptions, MMR,
+    },
+    store::{memory::InMemoryStore, IntoDynStore, Store, SubKey},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
 };
-use std::{collections::HashMap, sync::Arc};
 
-impl MMR {
-    pub async fn start_draft(&mut self) -> Result<DraftMMR, MMRError> {
+impl<H, S> MMR<H, S>
+where
+    H: Hasher + ?Sized,
+    S: IntoDynStore + ?Sized,
+{
+    pub async fn start_draft(&mut self) -> Result<DraftMMR<'_, H, S>, MMRError> {
         let store = InMemoryStore::default();
         let store = Arc::new(store);
         let hasher = self.hasher.clone();
@@ -13,7 +24,11 @@ impl MMR {
         let mut sub_mmrs = self.sub_mmrs.clone();
         sub_mmrs.push((self.elements_count.get().await?, self.get_metadata()));
 
-        let draft_mmr = MMR::new_stacked(store.clone(), hasher, None, sub_mmrs).await?;
+        let draft_mmr = MMR::new_stacked_typed(store.clone(), hasher, None, sub_mmrs)
+            .await?
+            .with_node_hashing(self.node_hashing)
+            .with_domain_separation(self.domain_separation)
+            .with_peak_bagging(self.peak_bagging);
 
         Ok(DraftMMR {
             store,
@@ -21,15 +36,124 @@ impl MMR {
             mmr: draft_mmr,
         })
     }
+
+    /// Applies a [`DraftDiff`] exported from a draft of another copy of this MMR
+    ///
+    /// The diff is replayed on top of the current peaks and rejected if it does not
+    /// start from the current state or if any of its hashes does not match
+    pub async fn apply_diff(&mut self, diff: &DraftDiff) -> Result<(), MMRError> {
+        let elements_count = self.elements_count.get().await?;
+        let leaves_count = self.leaves_count.get().await?;
+        if elements_count != diff.old_elements_count || leaves_count != diff.old_leaves_count {
+            return Err(MMRError::InvalidDraftDiff);
+        }
+
+        let mut peaks = self
+            .retrieve_peaks_hashes(find_peaks(elements_count), None)
+            .await?;
+        let old_root_hash =
+            self.calculate_root_hash(&self.bag_peaks_hashes(peaks.clone())?, elements_count)?;
+        if peaks != diff.old_peaks || old_root_hash != diff.old_root_hash {
+            return Err(MMRError::InvalidDraftDiff);
+        }
+
+        //? Replay the appends to recompute every node the diff claims to add
+        let mut replayed: HashMap<usize, String> = HashMap::new();
+        let mut last_element_idx = elements_count;
+        for (leaf_offset, (element_index, value)) in diff.leaves.iter().enumerate() {
+            if *element_index != last_element_idx + 1 {
+                return Err(MMRError::InvalidDraftDiff);
+            }
+            let new_elements = append_to_peaks(
+                self.hasher.as_ref(),
+                self.node_hashing,
+                self.domain_separation,
+                &mut peaks,
+                leaves_count + leaf_offset,
+                value.clone(),
+            )?;
+            for hash in new_elements {
+                last_element_idx += 1;
+                replayed.insert(last_element_idx, hash);
+            }
+        }
+
+        let nodes_match = diff.nodes.len() + diff.leaves.len() == replayed.len()
+            && diff
+                .nodes
+                .iter()
+                .all(|(idx, hash)| replayed.get(idx) == Some(hash));
+        let new_root_hash =
+            self.calculate_root_hash(&self.bag_peaks_hashes(peaks.clone())?, last_element_idx)?;
+        if !nodes_match
+            || last_element_idx != diff.new_elements_count
+            || leaves_count + diff.leaves.len() != diff.new_leaves_count
+            || peaks != diff.new_peaks
+            || new_root_hash != diff.new_root_hash
+        {
+            return Err(MMRError::InvalidDraftDiff);
+        }
+
+        self.ensure_hashing_metadata().await?;
+        self.hashes
+            .set_many(
+                replayed
+                    .into_iter()
+                    .map(|(idx, hash)| (SubKey::Usize(idx), hash))
+                    .collect(),
+            )
+            .await?;
+        self.elements_count.set(diff.new_elements_count).await?;
+        self.leaves_count.set(diff.new_leaves_count).await?;
+        self.root_hash.set(&new_root_hash, SubKey::None).await?;
+
+        Ok(())
+    }
+}
+
+/// The changes a [`DraftMMR`] would make to the MMR it was started from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DraftDiff {
+    pub old_elements_count: usize,
+    pub new_elements_count: usize,
+    pub old_leaves_count: usize,
+    pub new_leaves_count: usize,
+    /// The appended leaves as (element index, hash), in append order
+    pub leaves: Vec<(usize, String)>,
+    /// The internal nodes created by the appends as (element index, hash), in index order
+    pub nodes: Vec<(usize, String)>,
+    pub old_peaks: Vec<String>,
+    pub new_peaks: Vec<String>,
+    pub old_root_hash: String,
+    pub new_root_hash: String,
+}
+
+impl DraftDiff {
+    /// Encodes the diff so it can be sent to another process
+    pub fn to_json(&self) -> Result<String, MMRError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Decodes a diff produced by [`DraftDiff::to_json`]
+    pub fn from_json(json: &str) -> Result<Self, MMRError> {
+        Ok(serde_json::from_str(json)?)
+    }
 }
 
-pub struct DraftMMR<'a> {
+/// A stacked MMR over an MMR, whose appends are only written to it on commit
+///
+/// The draft reads from both the MMR and its own store, so its store is a trait object.
+pub struct DraftMMR<'a, H: ?Sized = dyn Hasher, S: ?Sized = dyn Store> {
     store: Arc<InMemoryStore>,
-    ref_mmr: &'a mut MMR,
-    pub mmr: MMR,
+    ref_mmr: &'a mut MMR<H, S>,
+    pub mmr: MMR<H>,
 }
 
-impl DraftMMR<'_> {
+impl<H, S> DraftMMR<'_, H, S>
+where
+    H: Hasher + ?Sized,
+    S: Store + ?Sized,
+{
     pub fn discard(self) {
         self.store.clear();
     }
@@ -48,4 +172,79 @@ impl DraftMMR<'_> {
         self.store.clear();
         Ok(())
     }
+
+    /// Computes what committing the draft would change in the referenced MMR
+    pub async fn diff(&self) -> Result<DraftDiff, MMRError> {
+        let old_elements_count = self.ref_mmr.elements_count.get().await?;
+        let old_leaves_count = self.ref_mmr.leaves_count.get().await?;
+        let new_elements_count = self.mmr.elements_count.get().await?;
+        let new_leaves_count = self.mmr.leaves_count.get().await?;
+
+        let leaves_indexes: HashSet<usize> = (old_leaves_count..new_leaves_count)
+            .map(map_leaf_index_to_element_index)
+            .collect();
+
+        let new_indexes: Vec<usize> = (old_elements_count + 1..=new_elements_count).collect();
+        let hashes = self
+            .mmr
+            .hashes
+            .get_many(new_indexes.iter().map(|&idx| SubKey::Usize(idx)).collect())
+            .await?;
+
+        let mut leaves = Vec::new();
+        let mut nodes = Vec::new();
+        for idx in new_indexes {
+            let hash = hashes
+                .get(&idx.to_string())
+                .cloned()
+                .ok_or(MMRError::NoHashFoundForIndex(idx))?;
+            if leaves_indexes.contains(&idx) {
+                leaves.push((idx, hash));
+            } else {
+                nodes.push((idx, hash));
+            }
+        }
+
+        let old_peaks = self
+            .ref_mmr
+            .get_peaks(PeaksOptions {
+                elements_count: Some(old_elements_count),
+                formatting_opts: None,
+            })
+            .await?;
+        let new_peaks = self
+            .mmr
+            .get_peaks(PeaksOptions {
+                elements_count: Some(new_elements_count),
+                formatting_opts: None,
+            })
+            .await?;
+
+        let old_root_hash = self.ref_mmr.calculate_root_hash(
+            &self.ref_mmr.bag_peaks_hashes(old_peaks.clone())?,
+            old_elements_count,
+        )?;
+        let new_root_hash = self.mmr.calculate_root_hash(
+            &self.mmr.bag_peaks_hashes(new_peaks.clone())?,
+            new_elements_count,
+        )?;
+
+        Ok(DraftDiff {
+            old_elements_count,
+            new_elements_count,
+            old_leaves_count,
+            new_leaves_count,
+            leaves,
+            nodes,
+            old_peaks,
+            new_peaks,
+            old_root_hash,
+            new_root_hash,
+        })
+    }
+
+    /// Exports the draft changeset, see [`DraftMMR::diff`] and [`MMR::apply_diff`]
+    pub async fn export_diff(&self) -> Result<String, MMRError> {
+        self.diff().await?.to_json()
+    }
 }
@@ -1,4 +1,10 @@
+#[cfg(feature = "compact_mmr")]
+pub mod compact;
 #[cfg(feature = "draft_mmr")]
 pub mod draft;
+#[cfg(feature = "sharded_mmr")]
+pub mod sharded;
+#[cfg(feature = "shared_mmr")]
+pub mod shared;
 #[cfg(feature = "stacked_mmr")]
 pub mod stacked;
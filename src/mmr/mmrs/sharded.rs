@@ -0,0 +1,274 @@
+use async_trait::async_trait;
+use std::{fmt::Debug, ops::Deref, sync::Arc};
+
+#[cfg(feature = "memory")]
+use parking_lot::Mutex;
+#[cfg(feature = "memory")]
+use std::collections::HashMap;
+#[cfg(feature = "sqlite")]
+use std::path::PathBuf;
+
+use crate::{
+    hasher::{DomainSeparation, Hasher, NodeHashing},
+    mmr::{AppendResult, MMRError, MmrMetadata, PeakBagging, SizesToMMRs, TreeMetadataKeys, MMR},
+    store::{InStoreTable, Store, StoreError, SubKey},
+};
+
+#[cfg(feature = "memory")]
+use crate::store::memory::InMemoryStore;
+#[cfg(feature = "sqlite")]
+use crate::store::sqlite::SQLiteStore;
+
+/// Creates (or reopens) the cold store that a frozen shard is moved to
+#[async_trait]
+pub trait ShardStoreFactory: Send + Sync + Debug {
+    /// Returns the store for the given shard id, the same id must always give the same data
+    async fn shard_store(&self, shard_id: &str) -> Result<Arc<dyn Store>, StoreError>;
+}
+
+/// Keeps every shard in its own [`InMemoryStore`]
+#[cfg(feature = "memory")]
+#[derive(Debug, Default)]
+pub struct InMemoryShardStores {
+    stores: Mutex<HashMap<String, Arc<InMemoryStore>>>,
+}
+
+#[cfg(feature = "memory")]
+#[async_trait]
+impl ShardStoreFactory for InMemoryShardStores {
+    async fn shard_store(&self, shard_id: &str) -> Result<Arc<dyn Store>, StoreError> {
+        let mut stores = self.stores.lock();
+        let store = stores
+            .entry(shard_id.to_string())
+            .or_insert_with(|| Arc::new(InMemoryStore::new(Some(shard_id))))
+            .clone();
+        Ok(store)
+    }
+}
+
+/// Keeps every shard in its own SQLite file, named after the shard id, inside `directory`
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SQLiteShardStores {
+    pub directory: PathBuf,
+}
+
+#[cfg(feature = "sqlite")]
+impl SQLiteShardStores {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[async_trait]
+impl ShardStoreFactory for SQLiteShardStores {
+    async fn shard_store(&self, shard_id: &str) -> Result<Arc<dyn Store>, StoreError> {
+        let path = self.directory.join(format!("{}.db", shard_id));
+        let store = SQLiteStore::new(&path.to_string_lossy(), Some(true), Some(shard_id)).await?;
+        Ok(Arc::new(store))
+    }
+}
+
+/// A stacked MMR that moves its active segment to a cold store every `shard_size` elements
+///
+/// The hot store keeps the counters, the root hash, the shard boundaries and the hashes of
+/// the active segment. Reads are routed to the right shard by element index.
+///
+/// The inner MMR is only reachable through `Deref`, so appends always go through the freeze check.
+#[derive(Debug)]
+pub struct ShardedMMR<H: ?Sized = dyn Hasher> {
+    mmr: MMR<H>,
+    pub shard_size: usize,
+    shard_stores: Arc<dyn ShardStoreFactory>,
+}
+
+impl ShardedMMR {
+    /// Opens the sharded MMR stored in `hot_store`, reattaching the shards it was frozen into
+    ///
+    /// Like [`MMR::open`], it keeps the node hashing, domain separation and peak bagging
+    /// recorded by its first append.
+    pub async fn open(
+        hot_store: Arc<dyn Store>,
+        hasher: Arc<dyn Hasher>,
+        mmr_id: Option<String>,
+        shard_size: usize,
+        shard_stores: Arc<dyn ShardStoreFactory>,
+    ) -> Result<Self, MMRError> {
+        ShardedMMR::open_typed(hot_store, hasher, mmr_id, shard_size, shard_stores).await
+    }
+
+    pub fn shard_id(mmr_id: &str, shard_index: usize) -> String {
+        format!("{}-shard-{}", mmr_id, shard_index)
+    }
+
+    pub fn shards_key(mmr_id: &str) -> String {
+        MMR::encode_store_key(mmr_id, TreeMetadataKeys::Shards, SubKey::None)
+    }
+}
+
+impl<H> ShardedMMR<H>
+where
+    H: Hasher + ?Sized,
+{
+    /// Opens a sharded MMR over a concrete hasher type, see [`ShardedMMR::open`]
+    pub async fn open_typed(
+        hot_store: Arc<dyn Store>,
+        hasher: Arc<H>,
+        mmr_id: Option<String>,
+        shard_size: usize,
+        shard_stores: Arc<dyn ShardStoreFactory>,
+    ) -> Result<Self, MMRError> {
+        if shard_size == 0 {
+            return Err(MMRError::InvalidElementCount);
+        }
+
+        let mmr = MMR::new_typed(hot_store.clone(), hasher.clone(), mmr_id);
+        let boundaries = hot_store
+            .get(&ShardedMMR::shards_key(&mmr.mmr_id))
+            .await?
+            .unwrap_or_default();
+
+        let mut sub_mmrs: SizesToMMRs = Vec::new();
+        for (shard_index, size) in boundaries.split(',').filter(|s| !s.is_empty()).enumerate() {
+            let size = size.parse::<usize>().map_err(StoreError::Parse)?;
+            let shard_id = ShardedMMR::shard_id(&mmr.mmr_id, shard_index);
+            sub_mmrs.push((
+                size,
+                MmrMetadata {
+                    store: shard_stores.shard_store(&shard_id).await?,
+                    mmr_id: shard_id,
+                    hasher: hasher.get_name(),
+                },
+            ));
+        }
+
+        let mut mmr = MMR::new_stacked_typed(hot_store, hasher, Some(mmr.mmr_id), sub_mmrs).await?;
+        mmr.restore_hashing_metadata().await?;
+
+        Ok(Self {
+            mmr,
+            shard_size,
+            shard_stores,
+        })
+    }
+
+    /// Sets how sibling nodes are combined, see [`MMR::with_node_hashing`]
+    pub fn with_node_hashing(mut self, node_hashing: NodeHashing) -> Self {
+        self.mmr.node_hashing = node_hashing;
+        self
+    }
+
+    /// Sets the domain separation, see [`MMR::with_domain_separation`]
+    pub fn with_domain_separation(mut self, domain_separation: DomainSeparation) -> Self {
+        self.mmr.domain_separation = domain_separation;
+        self
+    }
+
+    /// Sets how the peaks are bagged, see [`MMR::with_peak_bagging`]
+    pub fn with_peak_bagging(mut self, peak_bagging: PeakBagging) -> Self {
+        self.mmr.peak_bagging = peak_bagging;
+        self
+    }
+
+    /// The frozen shards, as the element count each one ends at and its metadata
+    pub fn shards(&self) -> &SizesToMMRs {
+        &self.mmr.sub_mmrs
+    }
+
+    /// The number of elements that are already frozen
+    pub fn frozen_elements_count(&self) -> usize {
+        self.mmr.sub_mmrs.last().map_or(0, |(size, _)| *size)
+    }
+
+    /// Appends to the hot segment and freezes it once it reaches `shard_size` elements
+    pub async fn append(&mut self, value: String) -> Result<AppendResult, MMRError> {
+        let result = self.mmr.append(value).await?;
+
+        if result.elements_count - self.frozen_elements_count() >= self.shard_size {
+            self.freeze().await?;
+        }
+
+        Ok(result)
+    }
+
+    /// Moves the hashes of the hot segment to a new shard store and starts a fresh segment
+    pub async fn freeze(&mut self) -> Result<(), MMRError> {
+        let elements_count = self.mmr.elements_count.get().await?;
+        let frozen_elements_count = self.frozen_elements_count();
+        if elements_count == frozen_elements_count {
+            return Ok(());
+        }
+
+        let shard_id = ShardedMMR::shard_id(&self.mmr.mmr_id, self.mmr.sub_mmrs.len());
+        let shard_store = self.shard_stores.shard_store(&shard_id).await?;
+
+        //? Copy the segment to the cold store
+        let segment: Vec<SubKey> = (frozen_elements_count + 1..=elements_count)
+            .map(SubKey::Usize)
+            .collect();
+        let hashes = self.mmr.hashes.get_many(segment.clone()).await?;
+        let (_, _, _, shard_hashes) = MMR::get_stores(&shard_id, shard_store.clone());
+        shard_hashes
+            .set_many(
+                hashes
+                    .into_iter()
+                    .map(|(sub_key, hash)| (SubKey::String(sub_key), hash))
+                    .collect(),
+            )
+            .await?;
+
+        //? Persist the new boundary and route reads through it
+        let mut sub_mmrs = self.mmr.sub_mmrs.clone();
+        sub_mmrs.push((
+            elements_count,
+            MmrMetadata {
+                mmr_id: shard_id,
+                store: shard_store,
+                hasher: self.mmr.hasher.get_name(),
+            },
+        ));
+        let boundaries = sub_mmrs
+            .iter()
+            .map(|(size, _)| size.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        self.mmr
+            .store
+            .set(&ShardedMMR::shards_key(&self.mmr.mmr_id), &boundaries)
+            .await?;
+
+        self.mmr = MMR::new_stacked_typed(
+            self.mmr.store.clone(),
+            self.mmr.hasher.clone(),
+            Some(self.mmr.mmr_id.clone()),
+            sub_mmrs,
+        )
+        .await?
+        .with_node_hashing(self.mmr.node_hashing)
+        .with_domain_separation(self.mmr.domain_separation)
+        .with_peak_bagging(self.mmr.peak_bagging);
+
+        //? Drop the segment from the hot store
+        let hot_keys: Vec<String> = segment
+            .into_iter()
+            .map(|sub_key| InStoreTable::get_full_key(&self.mmr.hashes.key, &sub_key.to_string()))
+            .collect();
+        self.mmr
+            .store
+            .delete_many(hot_keys.iter().map(AsRef::as_ref).collect())
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl<H: ?Sized> Deref for ShardedMMR<H> {
+    type Target = MMR<H>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.mmr
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    hasher::Hasher,
+    mmr::{AppendResult, MMRError, PeaksOptions, Proof, ProofOptions, MMR},
+    store::Store,
+};
+
+/// A cheaply clonable MMR handle that can be shared across tasks
+///
+/// Appends are serialized, while reads run concurrently against the elements count of the
+/// last completed append, so they never observe an append halfway through.
+#[derive(Debug)]
+pub struct SharedMMR<H: ?Sized = dyn Hasher, S: ?Sized = dyn Store> {
+    writer: Arc<Mutex<MMR<H, S>>>,
+    reader: Arc<MMR<H, S>>,
+    elements_count: Arc<AtomicUsize>,
+}
+
+impl<H: ?Sized, S: ?Sized> Clone for SharedMMR<H, S> {
+    fn clone(&self) -> Self {
+        Self {
+            writer: self.writer.clone(),
+            reader: self.reader.clone(),
+            elements_count: self.elements_count.clone(),
+        }
+    }
+}
+
+impl<H, S> SharedMMR<H, S>
+where
+    H: Hasher + ?Sized,
+    S: Store + ?Sized,
+{
+    pub async fn new(mmr: MMR<H, S>) -> Result<Self, MMRError> {
+        let elements_count = mmr.elements_count.get().await?;
+
+        Ok(Self {
+            reader: Arc::new(mmr.clone()),
+            writer: Arc::new(Mutex::new(mmr)),
+            elements_count: Arc::new(AtomicUsize::new(elements_count)),
+        })
+    }
+
+    /// The elements count that reads are currently done against
+    pub fn elements_count(&self) -> usize {
+        self.elements_count.load(Ordering::Acquire)
+    }
+
+    pub fn mmr_id(&self) -> &str {
+        &self.reader.mmr_id
+    }
+
+    pub async fn append(&self, value: String) -> Result<AppendResult, MMRError> {
+        let mut mmr = self.writer.lock().await;
+        let result = mmr.append(value).await?;
+        self.elements_count
+            .store(result.elements_count, Ordering::Release);
+        Ok(result)
+    }
+
+    pub async fn get_proof(
+        &self,
+        element_index: usize,
+        options: Option<ProofOptions>,
+    ) -> Result<Proof, MMRError> {
+        self.reader
+            .get_proof(element_index, Some(self.snapshot_options(options)))
+            .await
+    }
+
+    pub async fn get_proofs(
+        &self,
+        elements_indexes: Vec<usize>,
+        options: Option<ProofOptions>,
+    ) -> Result<Vec<Proof>, MMRError> {
+        self.reader
+            .get_proofs(elements_indexes, Some(self.snapshot_options(options)))
+            .await
+    }
+
+    /// Verifies the proof against [`SharedMMR::elements_count`], unless told otherwise
+    ///
+    /// Proofs, or options, with more elements than the snapshot are rejected with
+    /// [`MMRError::InvalidElementCount`].
+    pub async fn verify_proof(
+        &self,
+        proof: Proof,
+        element_value: String,
+        options: Option<ProofOptions>,
+    ) -> Result<bool, MMRError> {
+        let elements_count = self.elements_count();
+        let options = self.snapshot_options(options);
+        if proof.elements_count > elements_count
+            || options
+                .elements_count
+                .is_some_and(|count| count > elements_count)
+        {
+            return Err(MMRError::InvalidElementCount);
+        }
+
+        self.reader
+            .verify_proof(proof, element_value, Some(options))
+            .await
+    }
+
+    pub async fn get_peaks(&self, mut option: PeaksOptions) -> Result<Vec<String>, MMRError> {
+        option.elements_count = option.elements_count.or(Some(self.elements_count()));
+        self.reader.get_peaks(option).await
+    }
+
+    /// The root hash of the MMR at [`SharedMMR::elements_count`]
+    pub async fn get_root_hash(&self) -> Result<String, MMRError> {
+        let elements_count = self.elements_count();
+        let bag = self.reader.bag_the_peaks(Some(elements_count)).await?;
+        self.reader.calculate_root_hash(&bag, elements_count)
+    }
+
+    fn snapshot_options(&self, options: Option<ProofOptions>) -> ProofOptions {
+        let mut options = options.unwrap_or_default();
+        options.elements_count = options.elements_count.or(Some(self.elements_count()));
+        options
+    }
+}
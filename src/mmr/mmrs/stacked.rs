@@ -19,46 +19,14 @@ impl MMR {
         //? The sizes of the sub MMRs should be the stacks at size, not the actual current size
         sub_mmrs_metadata: SizesToMMRs,
     ) -> Result<Self, MMRError> {
-        let mut mmr = MMR::new(store, hasher, mmr_id);
-        let sub_mmrs_count = sub_mmrs_metadata.len();
-        let mut sub_mmrs: Vec<SubMMR> = Vec::with_capacity(sub_mmrs_count);
-
-        //? size here should be stacks at size
-        for (idx, (size, mmr_metadata)) in sub_mmrs_metadata.iter().enumerate() {
-            let (_, _, _, hashes_table) =
-                MMR::get_stores(&mmr_metadata.mmr_id, mmr_metadata.store.clone());
-
-            sub_mmrs.push(SubMMR {
-                size: *size,
-                store: mmr_metadata.store.clone(),
-                key: hashes_table.key.clone(),
-            });
-
-            //? Last sub MMR gets special treatment
-            if idx != sub_mmrs_count - 1 {
-                continue;
-            }
-
-            let elements_count = size;
-            let current_elements_count = mmr.elements_count.get().await?;
-
-            //? If the current MMR is already larger than the sub MMR, we don't need to do anything
-            if current_elements_count >= *elements_count {
-                continue;
-            }
-
-            let leaves_count = elements_count_to_leaf_count(*elements_count)?;
-
-            mmr.elements_count.set(*elements_count).await?;
-            mmr.leaves_count.set(leaves_count).await?;
-        }
-
-        mmr.hashes.get_store_and_full_key = MMR::get_store_and_full_key;
-        mmr.hashes.get_stores_and_full_keys = MMR::get_stores_and_full_keys;
-        mmr.hashes.sub_mmrs = Some(sub_mmrs);
-        mmr.sub_mmrs = sub_mmrs_metadata;
+        MMR::new_stacked_typed(store, hasher, mmr_id, sub_mmrs_metadata).await
+    }
 
-        Ok(mmr)
+    /// Routes the reads of a hashes table to the sub MMRs that hold each element
+    pub fn route_to_sub_mmrs(table: &mut InStoreTable, sub_mmrs: Vec<SubMMR>) {
+        table.get_store_and_full_key = MMR::get_store_and_full_key;
+        table.get_stores_and_full_keys = MMR::get_stores_and_full_keys;
+        table.sub_mmrs = Some(sub_mmrs);
     }
 
     pub fn get_store_and_full_key(
@@ -141,7 +109,7 @@ impl MMR {
 
             //? Sort the sub MMRs by size in ascending order
             let mut sub_mmrs = table.sub_mmrs.as_ref().unwrap().clone();
-            sub_mmrs.sort_by(|a, b| a.size.cmp(&b.size));
+            sub_mmrs.sort_by_key(|a| a.size);
 
             let mut use_mmr: Option<SubMMR> = None;
             for sub_mmr in sub_mmrs.iter() {
@@ -166,3 +134,57 @@ impl MMR {
             .collect())
     }
 }
+
+impl<H> MMR<H>
+where
+    H: Hasher + ?Sized,
+{
+    /// Creates a stacked MMR over a concrete hasher type, see [`MMR::new_stacked`]
+    ///
+    /// The sub MMRs can live in other stores, so the store stays a trait object.
+    pub async fn new_stacked_typed(
+        store: Arc<dyn Store>,
+        hasher: Arc<H>,
+        mmr_id: Option<String>,
+        sub_mmrs_metadata: SizesToMMRs,
+    ) -> Result<Self, MMRError> {
+        let mut mmr = MMR::new_typed(store, hasher, mmr_id);
+        let sub_mmrs_count = sub_mmrs_metadata.len();
+        let mut sub_mmrs: Vec<SubMMR> = Vec::with_capacity(sub_mmrs_count);
+
+        //? size here should be stacks at size
+        for (idx, (size, mmr_metadata)) in sub_mmrs_metadata.iter().enumerate() {
+            let (_, _, _, hashes_table) =
+                MMR::get_stores(&mmr_metadata.mmr_id, mmr_metadata.store.clone());
+
+            sub_mmrs.push(SubMMR {
+                size: *size,
+                store: mmr_metadata.store.clone(),
+                key: hashes_table.key.clone(),
+            });
+
+            //? Last sub MMR gets special treatment
+            if idx != sub_mmrs_count - 1 {
+                continue;
+            }
+
+            let elements_count = size;
+            let current_elements_count = mmr.elements_count.get().await?;
+
+            //? If the current MMR is already larger than the sub MMR, we don't need to do anything
+            if current_elements_count >= *elements_count {
+                continue;
+            }
+
+            let leaves_count = elements_count_to_leaf_count(*elements_count)?;
+
+            mmr.elements_count.set(*elements_count).await?;
+            mmr.leaves_count.set(leaves_count).await?;
+        }
+
+        MMR::route_to_sub_mmrs(&mut mmr.hashes, sub_mmrs);
+        mmr.sub_mmrs = sub_mmrs_metadata;
+
+        Ok(mmr)
+    }
+}
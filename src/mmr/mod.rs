@@ -5,6 +5,14 @@ mod formatting;
 pub use self::formatting::*;
 mod helpers;
 pub use self::helpers::*;
+mod leaf_range;
+pub use self::leaf_range::*;
+mod pruning;
+pub use self::pruning::*;
+mod snapshot;
+pub use self::snapshot::*;
+mod sorted;
+pub use self::sorted::*;
 
 mod core;
 pub use self::core::*;
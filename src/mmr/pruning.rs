@@ -0,0 +1,170 @@
+use parking_lot::RwLock;
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    hasher::Hasher,
+    store::{InStoreTable, Store, StoreError, SubKey},
+};
+
+use super::{
+    core::{MMRError, MMR},
+    helpers::{
+        element_index_to_leaf_index, find_peaks, find_siblings, map_leaf_index_to_element_index,
+        TreeMetadataKeys,
+    },
+};
+
+/// The state persisted by [`MMR::prune`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PruningState {
+    /// The elements count of the MMR when it was last pruned
+    pub pruned_elements_count: usize,
+    /// The leaves (element indexes) that can still be proven
+    pub pinned_leaves: HashSet<usize>,
+}
+
+/// The pruning state an MMR last read or wrote, `None` until it is first needed
+///
+/// Clones of an MMR share it. An MMR opened separately on the same store does not see later prunes.
+pub(crate) type PruningStateCache = Arc<RwLock<Option<Option<PruningState>>>>;
+
+impl MMR {
+    pub fn get_pruning_keys(mmr_id: &str) -> (String, String) {
+        (
+            MMR::encode_store_key(mmr_id, TreeMetadataKeys::PrunedElementsCount, SubKey::None),
+            MMR::encode_store_key(mmr_id, TreeMetadataKeys::PinnedLeaves, SubKey::None),
+        )
+    }
+}
+
+impl<H, S> MMR<H, S>
+where
+    H: Hasher + ?Sized,
+    S: Store + ?Sized,
+{
+    /// Deletes every hash that is not a peak and is not needed to prove one of `pinned_leaves`
+    ///
+    /// Leaves appended after the pruning can be proven as usual. Pruning again only accepts
+    /// leaves that are still provable. Returns the number of deleted hashes.
+    pub async fn prune(&mut self, pinned_leaves: Vec<usize>) -> Result<usize, MMRError> {
+        //? The hashes below the stacking point belong to the sub MMRs
+        #[cfg(feature = "stacked_mmr")]
+        if !self.sub_mmrs.is_empty() {
+            return Err(MMRError::StackedPruning);
+        }
+
+        let elements_count = self.elements_count.get().await?;
+        let previous_state = self.get_pruning_state().await?;
+
+        for &element_index in &pinned_leaves {
+            let is_leaf = element_index_to_leaf_index(element_index).is_ok_and(|leaf_index| {
+                map_leaf_index_to_element_index(leaf_index) == element_index
+            });
+            if !is_leaf || element_index > elements_count {
+                return Err(MMRError::InvalidElementIndex);
+            }
+            if let Some(state) = &previous_state {
+                if element_index <= state.pruned_elements_count
+                    && !state.pinned_leaves.contains(&element_index)
+                {
+                    return Err(MMRError::PrunedElement(element_index));
+                }
+            }
+        }
+
+        let mut retained: HashSet<usize> = find_peaks(elements_count).into_iter().collect();
+        for &element_index in &pinned_leaves {
+            retained.insert(element_index);
+            retained.extend(find_siblings(element_index, elements_count)?);
+        }
+
+        let to_delete: Vec<String> = (1..=elements_count)
+            .filter(|idx| !retained.contains(idx))
+            .map(|idx| InStoreTable::get_full_key(&self.hashes.key, &idx.to_string()))
+            .collect();
+        self.store
+            .delete_many(to_delete.iter().map(AsRef::as_ref).collect())
+            .await?;
+
+        let (pruned_elements_count_key, pinned_leaves_key) = MMR::get_pruning_keys(&self.mmr_id);
+        self.store
+            .set(&pruned_elements_count_key, &elements_count.to_string())
+            .await?;
+        self.store
+            .set(
+                &pinned_leaves_key,
+                &pinned_leaves
+                    .iter()
+                    .map(|idx| idx.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            )
+            .await?;
+
+        *self.pruning_state.write() = Some(Some(PruningState {
+            pruned_elements_count: elements_count,
+            pinned_leaves: pinned_leaves.into_iter().collect(),
+        }));
+
+        Ok(to_delete.len())
+    }
+
+    /// Returns the pruning state, or `None` if the MMR was never pruned
+    pub async fn get_pruning_state(&self) -> Result<Option<PruningState>, MMRError> {
+        let (pruned_elements_count_key, pinned_leaves_key) = MMR::get_pruning_keys(&self.mmr_id);
+
+        let pruned_elements_count = match self.store.get(&pruned_elements_count_key).await? {
+            Some(count) => count.parse::<usize>().map_err(StoreError::Parse)?,
+            None => return Ok(None),
+        };
+
+        let pinned_leaves = self
+            .store
+            .get(&pinned_leaves_key)
+            .await?
+            .unwrap_or_default()
+            .split(',')
+            .filter(|idx| !idx.is_empty())
+            .map(|idx| idx.parse::<usize>().map_err(StoreError::Parse))
+            .collect::<Result<HashSet<usize>, StoreError>>()?;
+
+        Ok(Some(PruningState {
+            pruned_elements_count,
+            pinned_leaves,
+        }))
+    }
+
+    /// Returns the pruning state, only reading the store the first time
+    async fn cached_pruning_state(&self) -> Result<Option<PruningState>, MMRError> {
+        if let Some(state) = self.pruning_state.read().clone() {
+            return Ok(state);
+        }
+
+        let state = self.get_pruning_state().await?;
+        *self.pruning_state.write() = Some(state.clone());
+        Ok(state)
+    }
+
+    /// Fails with [`MMRError::PrunedElement`] if one of the elements cannot be proven anymore
+    pub(crate) async fn ensure_provable(
+        &self,
+        elements_indexes: &[usize],
+        tree_size: usize,
+    ) -> Result<(), MMRError> {
+        let state = match self.cached_pruning_state().await? {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+
+        for &element_index in elements_indexes {
+            let is_pruned = element_index <= state.pruned_elements_count
+                && !state.pinned_leaves.contains(&element_index);
+            //? Peaks older than the pruning might be gone
+            if is_pruned || tree_size < state.pruned_elements_count {
+                return Err(MMRError::PrunedElement(element_index));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+use std::{collections::HashMap, path::Path, str::FromStr, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use tiny_keccak::{Hasher as _, Keccak};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+
+use crate::{
+    hasher::{DomainSeparation, Hasher, HashingFunction, NodeHashing},
+    store::{InStoreTable, Store, SubKey},
+};
+
+use super::{
+    core::{MMRError, MMR},
+    helpers::{append_to_peaks, leaf_count_to_mmr_size, PeakBagging},
+};
+
+/// The version of the snapshot format written by [`MMR::export_snapshot`]
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+//? Number of hashes read from or written to the store at once
+const SNAPSHOT_BATCH_SIZE: usize = 1000;
+
+/// The first line of a snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotHeader {
+    pub version: u32,
+    pub mmr_id: String,
+    pub hasher: String,
+    #[serde(default)]
+    pub node_hashing: NodeHashing,
+    #[serde(default)]
+    pub domain_separation: DomainSeparation,
+    #[serde(default)]
+    pub peak_bagging: PeakBagging,
+    pub leaves_count: usize,
+    pub elements_count: usize,
+    pub root_hash: String,
+}
+
+/// One element hash of a snapshot, written in element index order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SnapshotElement {
+    index: usize,
+    hash: String,
+}
+
+/// The last line of a snapshot, the keccak of every line before it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SnapshotChecksum {
+    checksum: String,
+}
+
+/// Keccak over the lines of a snapshot, newlines included
+struct SnapshotDigest(Keccak);
+
+impl SnapshotDigest {
+    fn new() -> Self {
+        Self(Keccak::v256())
+    }
+
+    fn update(&mut self, line: &str) {
+        self.0.update(line.as_bytes());
+        self.0.update(b"\n");
+    }
+
+    fn finalize(self) -> String {
+        let mut output = [0u8; 32];
+        self.0.finalize(&mut output);
+        format!("0x{}", hex::encode(output))
+    }
+}
+
+impl MMR {
+    /// Loads a snapshot written by [`MMR::export_snapshot`] into `store` under `mmr_id`
+    ///
+    /// The MMR keeps the node hashing and domain separation of the snapshot. Every hash is
+    /// recomputed with `hasher` while reading, and the import is rolled back if the hashes,
+    /// the root or the checksum do not match.
+    pub async fn import_snapshot<R: AsyncBufRead + Unpin>(
+        reader: R,
+        store: Arc<dyn Store>,
+        hasher: Arc<dyn Hasher>,
+        mmr_id: Option<String>,
+    ) -> Result<MMR, MMRError> {
+        MMR::import_snapshot_typed(reader, store, hasher, mmr_id).await
+    }
+
+    /// Imports an MMR from a snapshot file, see [`MMR::import_snapshot`]
+    pub async fn import_snapshot_file(
+        path: impl AsRef<Path>,
+        store: Arc<dyn Store>,
+        hasher: Arc<dyn Hasher>,
+        mmr_id: Option<String>,
+    ) -> Result<MMR, MMRError> {
+        let reader = BufReader::new(tokio::fs::File::open(path).await?);
+        MMR::import_snapshot(reader, store, hasher, mmr_id).await
+    }
+}
+
+impl<H, S> MMR<H, S>
+where
+    H: Hasher + ?Sized,
+    S: Store + ?Sized,
+{
+    /// Writes the MMR as a snapshot, one JSON object per line
+    ///
+    /// The header holds the metadata, counters and root, followed by every element hash and
+    /// a checksum. Hashes are read from the store in batches, so the MMR never has to fit in memory.
+    pub async fn export_snapshot<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> Result<SnapshotHeader, MMRError> {
+        let elements_count = self.elements_count.get().await?;
+        let header = SnapshotHeader {
+            version: SNAPSHOT_VERSION,
+            mmr_id: self.mmr_id.clone(),
+            hasher: self.hasher.get_name().to_string(),
+            node_hashing: self.node_hashing,
+            domain_separation: self.domain_separation,
+            peak_bagging: self.peak_bagging,
+            leaves_count: self.leaves_count.get().await?,
+            elements_count,
+            root_hash: self.calculate_root_hash(
+                &self.bag_the_peaks(Some(elements_count)).await?,
+                elements_count,
+            )?,
+        };
+
+        let mut digest = SnapshotDigest::new();
+        write_line(writer, &mut digest, &serde_json::to_string(&header)?).await?;
+
+        for batch_start in (1..=elements_count).step_by(SNAPSHOT_BATCH_SIZE) {
+            let batch_end = (batch_start + SNAPSHOT_BATCH_SIZE - 1).min(elements_count);
+            let hashes = self
+                .hashes
+                .get_many((batch_start..=batch_end).map(SubKey::Usize).collect())
+                .await?;
+
+            for index in batch_start..=batch_end {
+                let hash = hashes
+                    .get(&index.to_string())
+                    .cloned()
+                    .ok_or(MMRError::NoHashFoundForIndex(index))?;
+                let element = SnapshotElement { index, hash };
+                write_line(writer, &mut digest, &serde_json::to_string(&element)?).await?;
+            }
+        }
+
+        let checksum = SnapshotChecksum {
+            checksum: digest.finalize(),
+        };
+        writer
+            .write_all(format!("{}\n", serde_json::to_string(&checksum)?).as_bytes())
+            .await?;
+        writer.flush().await?;
+
+        Ok(header)
+    }
+
+    /// Imports a snapshot into an MMR over concrete hasher and store types, see [`MMR::import_snapshot`]
+    pub async fn import_snapshot_typed<R: AsyncBufRead + Unpin>(
+        reader: R,
+        store: Arc<S>,
+        hasher: Arc<H>,
+        mmr_id: Option<String>,
+    ) -> Result<Self, MMRError> {
+        let mut mmr = MMR::new_typed(store, hasher, mmr_id);
+        if mmr.elements_count.get().await? != 0 {
+            return Err(MMRError::NonEmptyMMR);
+        }
+
+        let mut written_elements = 0;
+        match mmr.load_snapshot(reader, &mut written_elements).await {
+            Ok(()) => Ok(mmr),
+            Err(error) => {
+                //? The import failed, so a failed rollback would only hide why
+                let _ = mmr.delete_imported_keys(written_elements).await;
+                Err(error)
+            }
+        }
+    }
+
+    /// Exports the MMR to a snapshot file, see [`MMR::export_snapshot`]
+    pub async fn export_snapshot_file(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<SnapshotHeader, MMRError> {
+        let mut writer = BufWriter::new(tokio::fs::File::create(path).await?);
+        self.export_snapshot(&mut writer).await
+    }
+
+    /// Replays the snapshot into the store, `written_elements` counting the hashes it may have written
+    async fn load_snapshot<R: AsyncBufRead + Unpin>(
+        &mut self,
+        reader: R,
+        written_elements: &mut usize,
+    ) -> Result<(), MMRError> {
+        let mut lines = reader.lines();
+        let mut digest = SnapshotDigest::new();
+
+        let line = next_line(&mut lines).await?;
+        digest.update(&line);
+        let header: SnapshotHeader = serde_json::from_str(&line)?;
+        if header.version != SNAPSHOT_VERSION {
+            return Err(MMRError::InvalidSnapshot(format!(
+                "unsupported version {}",
+                header.version
+            )));
+        }
+        if HashingFunction::from_str(&header.hasher)? != self.hasher.get_name() {
+            return Err(MMRError::InvalidSnapshot(format!(
+                "snapshot was made with {}",
+                header.hasher
+            )));
+        }
+        self.node_hashing = header.node_hashing;
+        self.domain_separation = header.domain_separation;
+        self.peak_bagging = header.peak_bagging;
+        if leaf_count_to_mmr_size(header.leaves_count) != header.elements_count {
+            return Err(MMRError::InvalidSnapshot(
+                "leaves and elements counts do not match".to_string(),
+            ));
+        }
+
+        //? Replay the appends, so every node is checked against its children
+        let mut peaks = Vec::new();
+        let mut batch = HashMap::with_capacity(SNAPSHOT_BATCH_SIZE);
+        let mut elements_count = 0;
+        for leaf_index in 0..header.leaves_count {
+            let leaf = self
+                .read_element(&mut lines, &mut digest, elements_count + 1)
+                .await?;
+            self.hasher.is_element_size_valid(&leaf)?;
+
+            let new_elements = append_to_peaks(
+                self.hasher.as_ref(),
+                self.node_hashing,
+                self.domain_separation,
+                &mut peaks,
+                leaf_index,
+                leaf,
+            )?;
+            for (offset, expected) in new_elements.into_iter().enumerate() {
+                elements_count += 1;
+                //? The leaf was already read, its parents still have to be
+                if offset > 0 {
+                    let hash = self
+                        .read_element(&mut lines, &mut digest, elements_count)
+                        .await?;
+                    if hash != expected {
+                        return Err(MMRError::InvalidSnapshot(format!(
+                            "element {} does not match its children",
+                            elements_count
+                        )));
+                    }
+                }
+                batch.insert(SubKey::Usize(elements_count), expected);
+            }
+
+            if batch.len() >= SNAPSHOT_BATCH_SIZE {
+                *written_elements = elements_count;
+                self.hashes.set_many(std::mem::take(&mut batch)).await?;
+            }
+        }
+
+        let root_hash = self.calculate_root_hash(&self.bag_peaks_hashes(peaks)?, elements_count)?;
+        if root_hash != header.root_hash {
+            return Err(MMRError::InvalidSnapshot("root hash mismatch".to_string()));
+        }
+
+        let checksum: SnapshotChecksum = serde_json::from_str(&next_line(&mut lines).await?)?;
+        if checksum.checksum != digest.finalize() {
+            return Err(MMRError::InvalidSnapshot("checksum mismatch".to_string()));
+        }
+        if lines.next_line().await?.is_some() {
+            return Err(MMRError::InvalidSnapshot(
+                "unexpected data after the checksum".to_string(),
+            ));
+        }
+
+        *written_elements = elements_count;
+        self.hashes.set_many(batch).await?;
+        self.record_hashing_metadata().await?;
+        self.elements_count.set(elements_count).await?;
+        self.leaves_count.set(header.leaves_count).await?;
+        self.root_hash.set(&root_hash, SubKey::None).await?;
+
+        Ok(())
+    }
+
+    /// Deletes the keys an import writes, the first `elements_count` hashes included
+    ///
+    /// The keys are listed rather than scanned, so stores without [`Store::scan_prefix`] can roll back too.
+    async fn delete_imported_keys(&self, elements_count: usize) -> Result<(), MMRError> {
+        let (leaves_count_key, elements_count_key, root_hash_key, _) =
+            MMR::get_store_keys(&self.mmr_id);
+        let (node_hashing_key, domain_separation_key) = MMR::get_hashing_keys(&self.mmr_id);
+
+        let mut keys: Vec<String> = (1..=elements_count)
+            .map(|idx| InStoreTable::get_full_key(&self.hashes.key, &idx.to_string()))
+            .collect();
+        keys.extend([
+            leaves_count_key,
+            elements_count_key,
+            root_hash_key,
+            node_hashing_key,
+            domain_separation_key,
+            MMR::get_peak_bagging_key(&self.mmr_id),
+        ]);
+
+        self.store
+            .delete_many(keys.iter().map(AsRef::as_ref).collect())
+            .await?;
+        Ok(())
+    }
+
+    async fn read_element<R: AsyncBufRead + Unpin>(
+        &self,
+        lines: &mut tokio::io::Lines<R>,
+        digest: &mut SnapshotDigest,
+        index: usize,
+    ) -> Result<String, MMRError> {
+        let line = next_line(lines).await?;
+        digest.update(&line);
+        let element: SnapshotElement = serde_json::from_str(&line)?;
+        if element.index != index {
+            return Err(MMRError::InvalidSnapshot(format!(
+                "expected element {}, found {}",
+                index, element.index
+            )));
+        }
+        Ok(element.hash)
+    }
+}
+
+async fn write_line<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    digest: &mut SnapshotDigest,
+    line: &str,
+) -> Result<(), MMRError> {
+    digest.update(line);
+    writer.write_all(line.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    Ok(())
+}
+
+async fn next_line<R: AsyncBufRead + Unpin>(
+    lines: &mut tokio::io::Lines<R>,
+) -> Result<String, MMRError> {
+    lines
+        .next_line()
+        .await?
+        .ok_or_else(|| MMRError::InvalidSnapshot("unexpected end of snapshot".to_string()))
+}
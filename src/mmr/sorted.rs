@@ -0,0 +1,270 @@
+use std::cmp::Ordering;
+
+use crate::{
+    hasher::{compare_hashes, DomainSeparation, Hasher, HasherError, NodeHashing},
+    store::{Store, SubKey},
+};
+
+use super::{
+    core::{MMRError, MMR},
+    helpers::{
+        compute_peak_hash, element_index_to_leaf_index, elements_count_to_leaf_count, find_peaks,
+        get_peak_info, map_leaf_index_to_element_index, HashingOptions, Proof, ProofOptions,
+    },
+};
+
+/// A proof of every leaf of an MMR of sorted keys that falls in `start..=end`
+///
+/// The leaves in the range are proven together with the leaves right before and after it,
+/// and all of them must be adjacent, so no key in the range can be left out. When the range
+/// holds no leaf, it proves that no key between `start` and `end` was appended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortedRangeProof {
+    pub start: String,
+    pub end: String,
+    /// The greatest leaf below `start`, none when `start` is below every leaf
+    pub left: Option<Proof>,
+    /// The leaves in the range, in order
+    pub leaves: Vec<Proof>,
+    /// The smallest leaf above `end`, none when `end` is above every leaf
+    pub right: Option<Proof>,
+    pub peaks_hashes: Vec<String>,
+    pub elements_count: usize,
+    pub node_hashing: NodeHashing,
+}
+
+impl SortedRangeProof {
+    /// Checks the proof against the trusted `root_hash` and `options`, without access to the MMR
+    ///
+    /// It relies on the keys having been appended in strictly increasing order. A proof
+    /// claiming other hashing settings than `options` is rejected.
+    pub fn verify(
+        &self,
+        hasher: &dyn Hasher,
+        root_hash: &str,
+        options: &HashingOptions,
+    ) -> Result<bool, MMRError> {
+        if options.domain_separation != DomainSeparation::Disabled {
+            return Err(MMRError::TaggedLeavesNotSearchable);
+        }
+        if self.node_hashing != options.node_hashing
+            || compare_hashes(&self.start, &self.end)? == Ordering::Greater
+        {
+            return Ok(false);
+        }
+        let bag = options
+            .peak_bagging
+            .bag(hasher, self.peaks_hashes.clone())?;
+        if options
+            .peak_bagging
+            .root(hasher, &bag, self.elements_count)?
+            != root_hash
+        {
+            return Ok(false);
+        }
+
+        if let Some(left) = &self.left {
+            if compare_hashes(&left.element_hash, &self.start)? != Ordering::Less {
+                return Ok(false);
+            }
+        }
+        if let Some(right) = &self.right {
+            if compare_hashes(&right.element_hash, &self.end)? != Ordering::Greater {
+                return Ok(false);
+            }
+        }
+        for leaf in &self.leaves {
+            if compare_hashes(&leaf.element_hash, &self.start)? == Ordering::Less
+                || compare_hashes(&leaf.element_hash, &self.end)? == Ordering::Greater
+            {
+                return Ok(false);
+            }
+        }
+
+        let sequence: Vec<&Proof> = self
+            .left
+            .iter()
+            .chain(self.leaves.iter())
+            .chain(self.right.iter())
+            .collect();
+        let mut leaf_indexes = Vec::with_capacity(sequence.len());
+        for proof in &sequence {
+            match self.verify_leaf(hasher, proof)? {
+                Some(leaf_index) => leaf_indexes.push(leaf_index),
+                None => return Ok(false),
+            }
+        }
+
+        //? Adjacent leaf positions, so no leaf can sit between the proven ones
+        let is_adjacent = leaf_indexes.windows(2).all(|pair| pair[0] + 1 == pair[1]);
+        if !is_adjacent {
+            return Ok(false);
+        }
+        for pair in sequence.windows(2) {
+            if compare_hashes(&pair[0].element_hash, &pair[1].element_hash)? != Ordering::Less {
+                return Ok(false);
+            }
+        }
+
+        let leaves_count = elements_count_to_leaf_count(self.elements_count)?;
+        let starts_at_first_leaf =
+            self.left.is_some() || leaf_indexes.first().is_none_or(|&i| i == 0);
+        let ends_at_last_leaf = self.right.is_some()
+            || leaf_indexes
+                .last()
+                .map_or(leaves_count == 0, |&i| i + 1 == leaves_count);
+
+        Ok(starts_at_first_leaf && ends_at_last_leaf)
+    }
+
+    /// Checks that this proves `key` was never appended, see [`SortedRangeProof::verify`]
+    pub fn verify_absence(
+        &self,
+        hasher: &dyn Hasher,
+        root_hash: &str,
+        key: &str,
+        options: &HashingOptions,
+    ) -> Result<bool, MMRError> {
+        Ok(self.leaves.is_empty()
+            && compare_hashes(&self.start, key)? == Ordering::Equal
+            && compare_hashes(&self.end, key)? == Ordering::Equal
+            && self.verify(hasher, root_hash, options)?)
+    }
+
+    /// Verifies the inclusion of a leaf, returning its leaf index
+    fn verify_leaf(&self, hasher: &dyn Hasher, proof: &Proof) -> Result<Option<usize>, MMRError> {
+        let leaf_index = element_index_to_leaf_index(proof.element_index)?;
+        if map_leaf_index_to_element_index(leaf_index) != proof.element_index
+            || proof.element_index > self.elements_count
+            || proof.elements_count != self.elements_count
+        {
+            return Ok(None);
+        }
+
+        let (peak_index, peak_height) = get_peak_info(self.elements_count, proof.element_index);
+        if proof.siblings_hashes.len() != peak_height {
+            return Ok(None);
+        }
+        let hash = compute_peak_hash(
+            hasher,
+            self.node_hashing,
+            DomainSeparation::Disabled,
+            proof.element_index,
+            proof.element_hash.clone(),
+            &proof.siblings_hashes,
+        )?;
+
+        Ok((self.peaks_hashes.get(peak_index) == Some(&hash)).then_some(leaf_index))
+    }
+}
+
+impl<H, S> MMR<H, S>
+where
+    H: Hasher + ?Sized,
+    S: Store + ?Sized,
+{
+    /// Proves which leaves fall in `start..=end`, for an MMR whose leaves are keys appended in
+    /// strictly increasing order
+    ///
+    /// The leaves are searched by their stored hashes, which are the keys themselves only
+    /// without domain separation.
+    pub async fn get_range_proof(
+        &self,
+        start: &str,
+        end: &str,
+    ) -> Result<SortedRangeProof, MMRError> {
+        if self.domain_separation != DomainSeparation::Disabled {
+            return Err(MMRError::TaggedLeavesNotSearchable);
+        }
+        if compare_hashes(start, end)? == Ordering::Greater {
+            return Err(MMRError::InvalidRange);
+        }
+
+        let elements_count = self.elements_count.get().await?;
+        let leaves_count = elements_count_to_leaf_count(elements_count)?;
+        let first_in_range = self
+            .partition_leaves(leaves_count, |key| {
+                Ok(compare_hashes(key, start)? == Ordering::Less)
+            })
+            .await?;
+        let first_after_range = self
+            .partition_leaves(leaves_count, |key| {
+                Ok(compare_hashes(key, end)? != Ordering::Greater)
+            })
+            .await?;
+
+        //? The range, extended by one leaf on each side when there is one
+        let from = first_in_range.saturating_sub(1);
+        let to = (first_after_range + 1).min(leaves_count);
+        let elements_indexes: Vec<usize> =
+            (from..to).map(map_leaf_index_to_element_index).collect();
+        let mut proofs = if elements_indexes.is_empty() {
+            Vec::new()
+        } else {
+            self.get_proofs(
+                elements_indexes,
+                Some(ProofOptions {
+                    elements_count: Some(elements_count),
+                    formatting_opts: None,
+                }),
+            )
+            .await?
+        };
+
+        let right = (first_after_range < leaves_count)
+            .then(|| proofs.pop())
+            .flatten();
+        let left = if first_in_range > 0 && !proofs.is_empty() {
+            Some(proofs.remove(0))
+        } else {
+            None
+        };
+        let peaks_hashes = self
+            .retrieve_peaks_hashes(find_peaks(elements_count), None)
+            .await?;
+
+        Ok(SortedRangeProof {
+            start: start.to_string(),
+            end: end.to_string(),
+            left,
+            leaves: proofs,
+            right,
+            peaks_hashes,
+            elements_count,
+            node_hashing: self.node_hashing,
+        })
+    }
+
+    /// Proves that `key` is not a leaf of an MMR of sorted keys, see [`MMR::get_range_proof`]
+    pub async fn get_absence_proof(&self, key: &str) -> Result<SortedRangeProof, MMRError> {
+        let proof = self.get_range_proof(key, key).await?;
+        if !proof.leaves.is_empty() {
+            return Err(MMRError::KeyPresent(key.to_string()));
+        }
+        Ok(proof)
+    }
+
+    /// Returns the number of leaves, from the first one, whose key matches `is_before`
+    async fn partition_leaves(
+        &self,
+        leaves_count: usize,
+        is_before: impl Fn(&str) -> Result<bool, HasherError>,
+    ) -> Result<usize, MMRError> {
+        let (mut low, mut high) = (0, leaves_count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let element_index = map_leaf_index_to_element_index(middle);
+            let key = self
+                .hashes
+                .get(SubKey::Usize(element_index))
+                .await?
+                .ok_or(MMRError::NoHashFoundForIndex(element_index))?;
+            if is_before(&key)? {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        Ok(low)
+    }
+}
@@ -1,5 +1,6 @@
 use async_trait::async_trait;
-use std::{collections::HashMap, fmt::Debug, num::ParseIntError};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::{collections::HashMap, fmt::Debug, num::ParseIntError, sync::Arc};
 use thiserror::Error;
 
 /// An error that can occur when using a store
@@ -24,8 +25,13 @@ pub enum StoreError {
     SQLite(#[from] sqlx::Error),
     #[error("Parse error: {0}")]
     Parse(#[from] ParseIntError),
+    #[error("Store does not support prefix scans")]
+    ScanPrefixUnsupported,
 }
 
+/// A stream of key/value pairs read from a store
+pub type KeyValueStream<'a> = BoxStream<'a, Result<(String, String), StoreError>>;
+
 /// Define common behavior for all stores
 #[async_trait]
 pub trait Store: Send + Sync + Debug {
@@ -48,4 +54,65 @@ pub trait Store: Send + Sync + Debug {
 
     /// Delete many values from the store
     async fn delete_many(&self, keys: Vec<&str>) -> Result<(), StoreError>;
+
+    /// Stream all the key/value pairs whose key starts with the prefix, ordered by key
+    ///
+    /// The default implementation yields [`StoreError::ScanPrefixUnsupported`], so stores
+    /// written before scans existed keep compiling
+    fn scan_prefix<'a>(&'a self, _prefix: &'a str) -> KeyValueStream<'a> {
+        stream::once(async { Err(StoreError::ScanPrefixUnsupported) }).boxed()
+    }
+
+    /// Set a value only if the current one is `expected`, `None` meaning the key is absent
+    ///
+    /// Returns whether the value was set. The default implementation is not atomic, stores
+    /// shared between processes should override it
+    async fn compare_and_set(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+    ) -> Result<bool, StoreError> {
+        if self.get(key).await?.as_deref() != expected {
+            return Ok(false);
+        }
+        self.set(key, value).await?;
+        Ok(true)
+    }
+
+    /// Add `by` to the number stored under the key, a missing key counting as 0
+    ///
+    /// Returns the new value. The default implementation retries [`Store::compare_and_set`]
+    async fn increment(&self, key: &str, by: usize) -> Result<usize, StoreError> {
+        loop {
+            let current = self.get(key).await?;
+            let new_value = current.as_deref().unwrap_or("0").parse::<usize>()? + by;
+            if self
+                .compare_and_set(key, current.as_deref(), &new_value.to_string())
+                .await?
+            {
+                return Ok(new_value);
+            }
+        }
+    }
+}
+
+/// A store that can be shared as an `Arc<dyn Store>`, for the MMRs that read from several stores
+///
+/// Implemented for every sized store and for `dyn Store` itself, so code generic over a store
+/// type still works with trait objects.
+pub trait IntoDynStore: Store {
+    fn into_dyn_store(self: Arc<Self>) -> Arc<dyn Store>;
+}
+
+impl<S: Store + 'static> IntoDynStore for S {
+    fn into_dyn_store(self: Arc<Self>) -> Arc<dyn Store> {
+        self
+    }
+}
+
+impl IntoDynStore for dyn Store {
+    fn into_dyn_store(self: Arc<Self>) -> Arc<dyn Store> {
+        self
+    }
 }
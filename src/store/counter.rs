@@ -6,17 +6,26 @@ use super::{Store, StoreError};
 ///
 /// It is used to keep track of the number of times for specific keys are used
 #[derive(Debug)]
-pub struct InStoreCounter {
+pub struct InStoreCounter<S: ?Sized = dyn Store> {
     /// The store that the counter is stored in
-    pub store: Arc<dyn Store>,
+    pub store: Arc<S>,
 
     /// The key of the counter
     pub key: String,
 }
 
-impl InStoreCounter {
+impl<S: ?Sized> Clone for InStoreCounter<S> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            key: self.key.clone(),
+        }
+    }
+}
+
+impl<S: Store + ?Sized> InStoreCounter<S> {
     /// Create a new counter
-    pub fn new(store: Arc<dyn Store>, key: String) -> Self {
+    pub fn new(store: Arc<S>, key: String) -> Self {
         Self { store, key }
     }
 
@@ -37,11 +46,22 @@ impl InStoreCounter {
         Ok(())
     }
 
-    /// Increment the count of the key
+    /// Increment the count of the key atomically
     pub async fn increment(&self) -> Result<usize, StoreError> {
-        let current_count = self.get().await?;
-        let new_count = current_count + 1;
-        self.set(new_count).await?;
-        Ok(new_count)
+        self.store.increment(&self.key, 1).await
+    }
+
+    /// Set the count of the key only if it is currently `expected`
+    ///
+    /// Returns whether the count was set
+    pub async fn compare_and_set(&self, expected: usize, count: usize) -> Result<bool, StoreError> {
+        let count = count.to_string();
+        //? A missing key counts as 0
+        if expected == 0 && self.store.compare_and_set(&self.key, None, &count).await? {
+            return Ok(true);
+        }
+        self.store
+            .compare_and_set(&self.key, Some(&expected.to_string()), &count)
+            .await
     }
 }
@@ -1,8 +1,9 @@
 use async_trait::async_trait;
+use futures::{stream, StreamExt};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 
-use crate::store::{Store, StoreError};
+use crate::store::{KeyValueStream, Store, StoreError};
 
 /// A store that is stored in memory
 #[derive(Debug)]
@@ -67,6 +68,42 @@ impl Store for InMemoryStore {
         }
         Ok(())
     }
+
+    fn scan_prefix<'a>(&'a self, prefix: &'a str) -> KeyValueStream<'a> {
+        let store = self.store.read();
+        let mut entries: Vec<(String, String)> = store
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        entries.sort();
+
+        stream::iter(entries.into_iter().map(Ok)).boxed()
+    }
+
+    async fn compare_and_set(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+    ) -> Result<bool, StoreError> {
+        let mut store = self.store.write();
+        if store.get(key).map(String::as_str) != expected {
+            return Ok(false);
+        }
+        store.insert(key.to_string(), value.to_string());
+        Ok(true)
+    }
+
+    async fn increment(&self, key: &str, by: usize) -> Result<usize, StoreError> {
+        let mut store = self.store.write();
+        let current = store
+            .get(key)
+            .map_or(Ok(0), |value| value.parse::<usize>())?;
+        let new_value = current + by;
+        store.insert(key.to_string(), new_value.to_string());
+        Ok(new_value)
+    }
 }
 
 impl InMemoryStore {
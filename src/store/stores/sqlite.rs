@@ -1,10 +1,11 @@
 use async_trait::async_trait;
+use futures::{stream, StreamExt, TryStreamExt};
 use sqlx::Error;
 use sqlx::{sqlite::SqliteConnectOptions, Pool, Row, Sqlite, SqlitePool};
 use std::collections::HashMap;
 use tokio::sync::Mutex;
 
-use crate::store::StoreError;
+use crate::store::{KeyValueStream, StoreError};
 
 use super::super::Store;
 
@@ -19,6 +20,9 @@ pub struct SQLiteStore {
 //? We use a smaller number to be safe.
 const MAX_VARIABLE_NUMBER: usize = 900;
 
+//? Number of rows fetched at once when scanning
+const SCAN_PAGE_SIZE: i64 = 1000;
+
 impl SQLiteStore {
     pub async fn new(
         path: &str,
@@ -174,4 +178,93 @@ impl Store for SQLiteStore {
 
         Ok(())
     }
+
+    fn scan_prefix<'a>(&'a self, prefix: &'a str) -> KeyValueStream<'a> {
+        //? Pages are fetched by key, so the lock is never held between two pages
+        stream::try_unfold(Some(None), move |last_key: Option<Option<String>>| async move {
+            let last_key = match last_key {
+                Some(last_key) => last_key,
+                None => return Ok::<_, StoreError>(None),
+            };
+
+            let pool = self.db.lock().await;
+            let query = match &last_key {
+                Some(last_key) => sqlx::query(
+                    "SELECT key, value FROM store WHERE key > ? AND substr(key, 1, ?) = ? ORDER BY key LIMIT ?",
+                )
+                .bind(last_key.clone()),
+                None => sqlx::query(
+                    "SELECT key, value FROM store WHERE key >= ? AND substr(key, 1, ?) = ? ORDER BY key LIMIT ?",
+                )
+                .bind(prefix),
+            };
+            let rows = query
+                .bind(prefix.chars().count() as i64)
+                .bind(prefix)
+                .bind(SCAN_PAGE_SIZE)
+                .fetch_all(&*pool)
+                .await?;
+
+            let page = rows
+                .into_iter()
+                .map(|row| Ok((row.try_get("key")?, row.try_get("value")?)))
+                .collect::<Result<Vec<(String, String)>, StoreError>>()?;
+
+            let next = match page.last() {
+                Some((key, _)) if page.len() as i64 == SCAN_PAGE_SIZE => Some(Some(key.clone())),
+                _ => None,
+            };
+            Ok(Some((stream::iter(page.into_iter().map(Ok)), next)))
+        })
+        .try_flatten()
+        .boxed()
+    }
+
+    async fn compare_and_set(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+    ) -> Result<bool, StoreError> {
+        let pool = self.db.lock().await;
+
+        let result = match expected {
+            Some(expected) => {
+                sqlx::query("UPDATE store SET value = ? WHERE key = ? AND value = ?")
+                    .bind(value)
+                    .bind(key)
+                    .bind(expected)
+                    .execute(&*pool)
+                    .await?
+            }
+            None => {
+                sqlx::query("INSERT OR IGNORE INTO store (key, value) VALUES (?, ?)")
+                    .bind(key)
+                    .bind(value)
+                    .execute(&*pool)
+                    .await?
+            }
+        };
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn increment(&self, key: &str, by: usize) -> Result<usize, StoreError> {
+        let pool = self.db.lock().await;
+
+        //? A single statement, so it stays atomic when several processes share the file
+        let row = sqlx::query(
+            r#"INSERT INTO store (key, value) VALUES (?, ?)
+            ON CONFLICT(key) DO UPDATE SET value = CAST(CAST(value AS INTEGER) + ? AS TEXT)
+            RETURNING value"#,
+        )
+        .bind(key)
+        .bind(by.to_string())
+        .bind(by as i64)
+        .fetch_one(&*pool)
+        .await?;
+
+        let value: String = row.try_get("value")?;
+        Ok(value.parse::<usize>()?)
+    }
 }
@@ -5,10 +5,9 @@ use std::hash::Hash;
 use std::sync::Arc;
 use thiserror::Error;
 
-type StoreArc = Arc<dyn Store>;
 type KeyList = Vec<String>;
-type StoreKeysPair = (StoreArc, KeyList);
-type StoreKeysList = Vec<StoreKeysPair>;
+type StoreKeysPair<S> = (Arc<S>, KeyList);
+type StoreKeysList<S> = Vec<StoreKeysPair<S>>;
 
 /// A sub key that is used to get a value from a store
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -18,12 +17,12 @@ pub enum SubKey {
     None,
 }
 
-impl ToString for SubKey {
-    fn to_string(&self) -> String {
+impl Display for SubKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
-            SubKey::String(sub_key) => sub_key.clone(),
-            SubKey::Usize(sub_key) => sub_key.to_string(),
-            SubKey::None => "".to_string(),
+            SubKey::String(sub_key) => write!(f, "{}", sub_key),
+            SubKey::Usize(sub_key) => write!(f, "{}", sub_key),
+            SubKey::None => write!(f, ""),
         }
     }
 }
@@ -35,18 +34,29 @@ impl Hash for SubKey {
 }
 
 /// A function that is used to get the full key and store for a given sub_key
-pub type GetFullKeyAndStoreFn =
-    fn(&InStoreTable, SubKey) -> Result<(Arc<dyn Store>, String), InStoreTableError>;
+pub type GetFullKeyAndStoreFn<S = dyn Store> =
+    fn(&InStoreTable<S>, SubKey) -> Result<(Arc<S>, String), InStoreTableError>;
 /// A function that is used to get the full keys and stores for a given list of sub_keys
-pub type GetFullKeysAndStoresFn =
-    fn(&InStoreTable, Vec<SubKey>) -> Result<Vec<(Arc<dyn Store>, Vec<String>)>, InStoreTableError>;
+pub type GetFullKeysAndStoresFn<S = dyn Store> =
+    fn(&InStoreTable<S>, Vec<SubKey>) -> Result<Vec<(Arc<S>, Vec<String>)>, InStoreTableError>;
 
 #[cfg(feature = "stacked_mmr")]
-#[derive(Debug, Clone)]
-pub struct SubMMR {
+#[derive(Debug)]
+pub struct SubMMR<S: ?Sized = dyn Store> {
     pub size: usize,
     pub key: String,
-    pub store: Arc<dyn Store>,
+    pub store: Arc<S>,
+}
+
+#[cfg(feature = "stacked_mmr")]
+impl<S: ?Sized> Clone for SubMMR<S> {
+    fn clone(&self) -> Self {
+        Self {
+            size: self.size,
+            key: self.key.clone(),
+            store: self.store.clone(),
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -88,12 +98,12 @@ pub enum InStoreTableError {
 }
 
 /// A table that is stored in a store
-#[derive(Debug, Clone)]
-pub struct InStoreTable {
+#[derive(Debug)]
+pub struct InStoreTable<S: ?Sized = dyn Store> {
     /// Always use this store for setters
     ///
     /// For getters use the store provided by the get_full_key... functions
-    pub store: Arc<dyn Store>,
+    pub store: Arc<S>,
     /// Always use this key for setters
     ///
     /// For getters use the key provided by the get_full_key... functions
@@ -101,18 +111,38 @@ pub struct InStoreTable {
     /// This function is used to get the full key and store for a given sub_key
     ///
     /// The default implementation is to use the store and key provided by the InStoreTable
-    pub get_store_and_full_key: GetFullKeyAndStoreFn,
+    pub get_store_and_full_key: GetFullKeyAndStoreFn<S>,
     /// This function is used to get the full keys and stores for a given list of sub_keys
     ///
     /// The default implementation is to use the store and key provided by the InStoreTable
-    pub get_stores_and_full_keys: GetFullKeysAndStoresFn,
+    pub get_stores_and_full_keys: GetFullKeysAndStoresFn<S>,
     #[cfg(feature = "stacked_mmr")]
-    pub sub_mmrs: Option<Vec<SubMMR>>,
+    pub sub_mmrs: Option<Vec<SubMMR<S>>>,
+}
+
+impl<S: ?Sized> Clone for InStoreTable<S> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            key: self.key.clone(),
+            get_store_and_full_key: self.get_store_and_full_key,
+            get_stores_and_full_keys: self.get_stores_and_full_keys,
+            #[cfg(feature = "stacked_mmr")]
+            sub_mmrs: self.sub_mmrs.clone(),
+        }
+    }
 }
 
 impl InStoreTable {
+    /// Get the full key for a given sub_key/key
+    pub fn get_full_key(key: &str, sub_key: &str) -> String {
+        format!("{}{}", key, sub_key)
+    }
+}
+
+impl<S: Store + ?Sized> InStoreTable<S> {
     /// Create a new table
-    pub fn new(store: Arc<dyn Store>, key: String) -> Self {
+    pub fn new(store: Arc<S>, key: String) -> Self {
         Self {
             store,
             key,
@@ -123,16 +153,11 @@ impl InStoreTable {
         }
     }
 
-    /// Get the full key for a given sub_key/key
-    pub fn get_full_key(key: &str, sub_key: &str) -> String {
-        format!("{}{}", key, sub_key)
-    }
-
     /// Get the full key and store for a given sub_key
     pub fn default_get_store_and_full_key(
         &self,
         sub_key: SubKey,
-    ) -> Result<(Arc<dyn Store>, String), InStoreTableError> {
+    ) -> Result<(Arc<S>, String), InStoreTableError> {
         let new_sub_key = sub_key.to_string();
         Ok((
             self.store.clone(),
@@ -144,7 +169,7 @@ impl InStoreTable {
     pub fn default_get_stores_and_full_keys(
         &self,
         sub_keys: Vec<SubKey>,
-    ) -> Result<StoreKeysList, InStoreTableError> {
+    ) -> Result<StoreKeysList<S>, InStoreTableError> {
         let sub_keys: Vec<String> = sub_keys
             .into_iter()
             .map(|sub_key| InStoreTable::get_full_key(&self.key, &sub_key.to_string()))
@@ -1,4 +1,6 @@
-use accumulators::hasher::{keccak::KeccakHasher, Hasher};
+use accumulators::hasher::{
+    compare_hashes, keccak::KeccakHasher, DomainSeparation, Hasher, HasherError, NodeHashing,
+};
 
 #[cfg(test)]
 mod test {
@@ -149,4 +151,141 @@ mod test {
             "0xf6770da1453b562908e206793d4b1f99237e8177d7a4403dfdb8012972912b61".to_string()
         );
     }
+
+    #[test]
+    fn hashes_sorted_pairs() {
+        let hasher = KeccakHasher::new();
+        let (a, b) = ("0x0f".to_string(), "0xa".to_string());
+
+        assert_eq!(
+            NodeHashing::Sorted
+                .hash_pair(&hasher, a.clone(), b.clone())
+                .unwrap(),
+            NodeHashing::Sorted
+                .hash_pair(&hasher, b.clone(), a.clone())
+                .unwrap()
+        );
+        assert_eq!(
+            NodeHashing::Sorted
+                .hash_pair(&hasher, a.clone(), b.clone())
+                .unwrap(),
+            hasher.hash(vec![b.clone(), a.clone()]).unwrap()
+        );
+        assert_eq!(
+            NodeHashing::Positional
+                .hash_pair(&hasher, a.clone(), b.clone())
+                .unwrap(),
+            hasher.hash(vec![a, b]).unwrap()
+        );
+    }
+
+    #[test]
+    fn compares_hashes_by_value() {
+        assert_eq!(
+            compare_hashes("0x00ff", "0xFE").unwrap(),
+            std::cmp::Ordering::Greater
+        );
+        assert_eq!(
+            compare_hashes("0x0", "0x").unwrap(),
+            std::cmp::Ordering::Equal
+        );
+        assert_eq!(
+            compare_hashes("0x10", "0x9").unwrap(),
+            std::cmp::Ordering::Greater
+        );
+        //? Like the hashers, values without `0x` are decimal
+        assert_eq!(
+            compare_hashes("10", "0xf").unwrap(),
+            std::cmp::Ordering::Less
+        );
+        assert_eq!(
+            compare_hashes("16", "0x10").unwrap(),
+            std::cmp::Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn rejects_non_hex_hashes() {
+        for (a, b) in [
+            ("0xzz", "0x1"),
+            ("0x1", "0x1g"),
+            ("", "0x1"),
+            ("0x-1", "0x1"),
+            ("ff", "0x1"),
+        ] {
+            assert!(matches!(
+                compare_hashes(a, b),
+                Err(HasherError::InvalidHash(_))
+            ));
+        }
+        assert!(matches!(
+            NodeHashing::Sorted.hash_pair(
+                &KeccakHasher::new(),
+                "0x1".to_string(),
+                "key".to_string()
+            ),
+            Err(HasherError::InvalidHash(_))
+        ));
+    }
+
+    #[test]
+    fn separates_leaf_and_node_domains() {
+        let hasher = KeccakHasher::new();
+        let (left, right) = ("0x1".to_string(), "0x2".to_string());
+
+        assert_eq!(
+            DomainSeparation::Disabled
+                .hash_leaf(&hasher, left.clone())
+                .unwrap(),
+            left
+        );
+        assert_eq!(
+            DomainSeparation::Disabled
+                .hash_node(
+                    &hasher,
+                    NodeHashing::Positional,
+                    left.clone(),
+                    right.clone()
+                )
+                .unwrap(),
+            hasher.hash(vec![left.clone(), right.clone()]).unwrap()
+        );
+
+        let tagged_leaf = DomainSeparation::Tagged
+            .hash_leaf(&hasher, left.clone())
+            .unwrap();
+        assert_eq!(
+            tagged_leaf,
+            hasher.hash(vec!["0x0".to_string(), left.clone()]).unwrap()
+        );
+        let tagged_node = DomainSeparation::Tagged
+            .hash_node(&hasher, NodeHashing::Sorted, right.clone(), left.clone())
+            .unwrap();
+        assert_eq!(
+            tagged_node,
+            hasher
+                .hash(vec!["0x1".to_string(), left.clone(), right])
+                .unwrap()
+        );
+        assert_ne!(tagged_leaf, tagged_node);
+    }
+
+    #[test]
+    fn hashes_raw_data() {
+        let hasher = KeccakHasher::new();
+
+        assert_eq!(
+            hasher.hash_data(&[]).unwrap(),
+            "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+
+        //? Longer than a block, like an RLP encoded header
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(
+            hasher.hash_data(&data).unwrap(),
+            hasher
+                .hash_single(&format!("0x{}", hex::encode(&data)))
+                .unwrap()
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use accumulators::hasher::{stark_pedersen::StarkPedersenHasher, Hasher};
+use starknet::core::{crypto::pedersen_hash, types::FieldElement};
 
 #[test]
 fn should_compute_a_hash() {
@@ -29,3 +30,65 @@ fn should_throw() {
     assert!(hasher.is_element_size_valid(&a).is_err());
     hasher.hash(vec![a, b]).unwrap();
 }
+
+#[test]
+fn should_hash_data_as_a_pedersen_chain() {
+    let hasher = StarkPedersenHasher::new();
+    let data: Vec<u8> = (0..32).collect();
+
+    //? Cairo's hash_state: chain from zero over the serialized `ByteArray`, then hash the number of elements
+    let elements = [
+        FieldElement::from(1u64),
+        FieldElement::from_byte_slice_be(&data[..31]).unwrap(),
+        FieldElement::from_byte_slice_be(&data[31..]).unwrap(),
+        FieldElement::from(1u64),
+    ];
+    let chain = elements.iter().fold(FieldElement::ZERO, |hash, element| {
+        pedersen_hash(&hash, element)
+    });
+    let expected = pedersen_hash(&chain, &FieldElement::from(elements.len()));
+
+    assert_eq!(
+        hasher.hash_data(&data).unwrap(),
+        format!("0x{}", hex::encode(expected.to_bytes_be()))
+    );
+}
+
+//? Computed with starknet-types-core 0.1.5, the Pedersen the Cairo VM runs its builtin with,
+//? over the serialization of a Cairo `ByteArray`, see the Cairo function in src/mmr/README.md
+#[test]
+fn should_hash_data_like_a_cairo_byte_array() {
+    let hasher = StarkPedersenHasher::new();
+    let vectors = [
+        (
+            0,
+            "0x00ba8cc6e828441028f48901de0bdb28a41b043e873062de3e6a44c7f6a93543",
+        ),
+        (
+            5,
+            "0x07b4326e3fd0fdfc580196c19979c40c625ca7bce5212be56179f6dd03863884",
+        ),
+        (
+            31,
+            "0x0185e1a57c0a57d593b52f190b81f4b2dfc1898410bdff256e1b7c43dd748361",
+        ),
+        (
+            32,
+            "0x0166d733b4c446bb3b565a42745d8bee8eff59d1a0696b7833b38063693eadf3",
+        ),
+        (
+            70,
+            "0x048571f84c90fca676058b6d5f4fc3cdeeeb8aeeb2df9bdae12886ae347ad054",
+        ),
+    ];
+
+    for (length, expected) in vectors {
+        let data: Vec<u8> = (0..length).collect();
+        assert_eq!(
+            hasher.hash_data(&data).unwrap(),
+            expected,
+            "{} bytes",
+            length
+        );
+    }
+}
@@ -1,4 +1,6 @@
 use accumulators::hasher::{stark_poseidon::StarkPoseidonHasher, Hasher};
+use starknet::core::types::FieldElement;
+use starknet_crypto::poseidon_hash_many;
 
 #[test]
 fn should_compute_a_hash() {
@@ -183,3 +185,85 @@ fn should_throw_on_empty_array() {
 
     assert!(hasher.hash(vec![]).is_err());
 }
+
+#[test]
+fn should_hash_data_as_a_serialized_byte_array() {
+    let hasher = StarkPoseidonHasher::new(Some(false));
+
+    //? No full word, the pending word and its length
+    let expected = poseidon_hash_many(&[
+        FieldElement::ZERO,
+        FieldElement::from(0x1234u64),
+        FieldElement::from(2u64),
+    ]);
+    assert_eq!(
+        hasher.hash_data(&[0x12, 0x34]).unwrap(),
+        format!("{:#x}", expected)
+    );
+    assert_eq!(
+        hasher.hash_data(&[]).unwrap(),
+        format!(
+            "{:#x}",
+            poseidon_hash_many(&[FieldElement::ZERO, FieldElement::ZERO, FieldElement::ZERO])
+        )
+    );
+}
+
+#[test]
+fn should_hash_data_longer_than_a_felt() {
+    let hasher = StarkPoseidonHasher::new(Some(false));
+    let data: Vec<u8> = (0..70).collect();
+
+    let expected = poseidon_hash_many(&[
+        FieldElement::from(2u64),
+        FieldElement::from_byte_slice_be(&data[..31]).unwrap(),
+        FieldElement::from_byte_slice_be(&data[31..62]).unwrap(),
+        FieldElement::from_byte_slice_be(&data[62..]).unwrap(),
+        FieldElement::from(8u64),
+    ]);
+    assert_eq!(hasher.hash_data(&data).unwrap(), format!("{:#x}", expected));
+    //? One byte more changes both the pending word and its length
+    assert_ne!(
+        hasher.hash_data(&data[..69]).unwrap(),
+        hasher.hash_data(&data).unwrap()
+    );
+}
+
+//? Computed with starknet-types-core 0.1.5, the Poseidon the Cairo VM runs its builtin with,
+//? over the serialization of a Cairo `ByteArray`, see the Cairo function in src/mmr/README.md
+#[test]
+fn should_hash_data_like_a_cairo_byte_array() {
+    let hasher = StarkPoseidonHasher::new(Some(false));
+    let vectors = [
+        (
+            0,
+            "0x29aee7812642221479b7e8af204ceaa5a7b7e113349fc8fb93e6303b477eb4d",
+        ),
+        (
+            5,
+            "0x66f0700cfb798e2ea36b70efea2efabe08aaa043e859e66b895144b611da05",
+        ),
+        (
+            31,
+            "0x85e9284a239c4e6efc4ae3d63e5feca29a4c5243065c9a369c2d19701ecaf",
+        ),
+        (
+            32,
+            "0x15f91e43d754e22ee5dd3b018a9de382f3efc8501ca515767f3fc21514bfff0",
+        ),
+        (
+            70,
+            "0xe3298baa95e3907b0e0ec0c9255f7a57feee9b05d1a9d4f83ef6f7a8299ab0",
+        ),
+    ];
+
+    for (length, expected) in vectors {
+        let data: Vec<u8> = (0..length).collect();
+        assert_eq!(
+            hasher.hash_data(&data).unwrap(),
+            expected,
+            "{} bytes",
+            length
+        );
+    }
+}
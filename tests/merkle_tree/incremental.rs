@@ -2,10 +2,18 @@
 mod tests {
     use std::sync::Arc;
 
+    use futures::TryStreamExt;
+
     use accumulators::{
-        hasher::stark_poseidon::StarkPoseidonHasher,
-        merkle_tree::incremental::IncrementalMerkleTree,
-        store::{sqlite::SQLiteStore, SubKey},
+        hasher::{
+            stark_pedersen::StarkPedersenHasher, stark_poseidon::StarkPoseidonHasher,
+            DomainSeparation, Hasher, NodeHashing,
+        },
+        merkle_tree::incremental::{
+            IncrementalMerkleProof, IncrementalMerkleTree, IncrementalMerkleTreeError,
+            IncrementalMerkleTreeOptions, TreeMetadataKeys,
+        },
+        store::{memory::InMemoryStore, sqlite::SQLiteStore, Store, SubKey},
     };
 
     #[tokio::test]
@@ -187,6 +195,648 @@ mod tests {
         assert!(is_valid);
     }
 
+    #[tokio::test]
+    async fn sorted_node_hashing() {
+        let store = Arc::new(InMemoryStore::new(Some("test")));
+        let hasher = StarkPoseidonHasher::new(Some(false));
+
+        let tree = IncrementalMerkleTree::initialize_with_node_hashing(
+            16,
+            "0x0".to_string(),
+            hasher.clone(),
+            store,
+            None,
+            NodeHashing::Sorted,
+        )
+        .await
+        .unwrap();
+
+        for i in 0..16 {
+            let path = tree.get_inclusion_proof(i).await.unwrap();
+            tree.update(i, "0x0".to_string(), format!("0x{:x}", i + 1), path)
+                .await
+                .unwrap();
+        }
+
+        let path = tree.get_inclusion_proof(5).await.unwrap();
+        assert!(tree.verify_proof(5, "0x6", &path).await.unwrap());
+        //? Sorted pairs do not depend on the position
+        assert!(tree.verify_proof(4, "0x6", &path).await.unwrap());
+        assert!(!tree.verify_proof(5, "0x5", &path).await.unwrap());
+
+        let sorted_root = path
+            .iter()
+            .try_fold("0x6".to_string(), |hash, sibling| {
+                NodeHashing::Sorted.hash_pair(&hasher, hash, sibling.clone())
+            })
+            .unwrap();
+        assert_eq!(tree.get_root().await.unwrap(), sorted_root);
+
+        let mut indexes = vec![1, 6, 11];
+        let mut values = indexes
+            .iter()
+            .map(|i| format!("0x{:x}", i + 1))
+            .collect::<Vec<_>>();
+        let mut multiproof = tree
+            .get_inclusion_multi_proof(indexes.clone())
+            .await
+            .unwrap();
+        assert!(tree
+            .verify_multi_proof(&mut indexes, &mut values, &mut multiproof)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn domain_separation() {
+        let store = Arc::new(InMemoryStore::new(Some("test")));
+        let hasher = StarkPoseidonHasher::new(Some(false));
+
+        let tree = IncrementalMerkleTree::initialize_with_hashing(
+            8,
+            "0x0".to_string(),
+            hasher.clone(),
+            store.clone(),
+            None,
+            NodeHashing::Positional,
+            DomainSeparation::Tagged,
+        )
+        .await
+        .unwrap();
+
+        let (node_hashing_key, domain_separation_key) =
+            IncrementalMerkleTree::<StarkPoseidonHasher>::get_hashing_keys(&tree.mmr_id);
+        assert_eq!(
+            store.get(&node_hashing_key).await.unwrap(),
+            Some("positional".to_string())
+        );
+        assert_eq!(
+            store.get(&domain_separation_key).await.unwrap(),
+            Some("tagged".to_string())
+        );
+
+        let (leaf_tag, _) = hasher.get_domain_tags();
+        assert_eq!(
+            tree.nodes
+                .get(SubKey::String("3:0".to_string()))
+                .await
+                .unwrap(),
+            Some(hasher.hash(vec![leaf_tag, "0x0".to_string()]).unwrap())
+        );
+
+        let path = tree.get_inclusion_proof(3).await.unwrap();
+        tree.update(3, "0x0".to_string(), "0x7".to_string(), path)
+            .await
+            .unwrap();
+
+        let path = tree.get_inclusion_proof(3).await.unwrap();
+        assert!(tree.verify_proof(3, "0x7", &path).await.unwrap());
+        assert!(!tree.verify_proof(3, "0x0", &path).await.unwrap());
+
+        let mut indexes = vec![2, 3];
+        let mut values = vec!["0x0".to_string(), "0x7".to_string()];
+        let mut multiproof = tree
+            .get_inclusion_multi_proof(indexes.clone())
+            .await
+            .unwrap();
+        assert!(tree
+            .verify_multi_proof(&mut indexes, &mut values, &mut multiproof)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn typed_proof_verified_without_the_tree() {
+        let store = Arc::new(InMemoryStore::new(Some("test")));
+        let hasher = StarkPoseidonHasher::new(Some(false));
+
+        let tree = IncrementalMerkleTree::initialize_with_hashing(
+            16,
+            "0x0".to_string(),
+            hasher.clone(),
+            store,
+            None,
+            NodeHashing::Sorted,
+            DomainSeparation::Tagged,
+        )
+        .await
+        .unwrap();
+        let path = tree.get_inclusion_proof(5).await.unwrap();
+        tree.update(5, "0x0".to_string(), "0x5".to_string(), path)
+            .await
+            .unwrap();
+
+        let proof = tree.get_proof(5, "0x5").await.unwrap();
+        assert_eq!(proof.tree_depth, 4);
+        assert_eq!(proof.siblings.len(), 4);
+        assert_eq!(proof.root, tree.get_root().await.unwrap());
+
+        //? A recipient only holds the serialized proof and a hasher
+        //? and the root and settings it trusts
+        let root = tree.get_root().await.unwrap();
+        let options = tree.options();
+        let received: IncrementalMerkleProof =
+            serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+        assert!(received.verify(&hasher, &root, &options).unwrap());
+
+        let mut tampered = received.clone();
+        tampered.value = "0x6".to_string();
+        assert!(!tampered.verify(&hasher, &root, &options).unwrap());
+        let mut tampered = received.clone();
+        tampered.siblings.pop();
+        assert!(!tampered.verify(&hasher, &root, &options).unwrap());
+        let mut tampered = received.clone();
+        tampered.index = 16;
+        assert!(!tampered.verify(&hasher, &root, &options).unwrap());
+
+        //? A forged proof is consistent with its own root, which is never trusted
+        let other = IncrementalMerkleTree::initialize_with_hashing(
+            16,
+            "0x0".to_string(),
+            hasher.clone(),
+            Arc::new(InMemoryStore::new(Some("other"))),
+            None,
+            NodeHashing::Sorted,
+            DomainSeparation::Tagged,
+        )
+        .await
+        .unwrap();
+        other
+            .update_many(vec![(5, "0x6".to_string())])
+            .await
+            .unwrap();
+        let forged = other.get_proof(5, "0x6").await.unwrap();
+        assert!(forged.verify(&hasher, &forged.root, &options).unwrap());
+        assert!(!forged.verify(&hasher, &root, &options).unwrap());
+
+        //? Nor are its hashing settings
+        let mut forged = received.clone();
+        forged.node_hashing = NodeHashing::Positional;
+        assert!(!forged.verify(&hasher, &root, &options).unwrap());
+        assert!(!received
+            .verify(&hasher, &root, &IncrementalMerkleTreeOptions::default())
+            .unwrap());
+
+        assert!(matches!(
+            tree.get_proof(5, "0x0").await,
+            Err(IncrementalMerkleTreeError::InvalidLeafValue)
+        ));
+        assert!(matches!(
+            tree.get_proof(16, "0x0").await,
+            Err(IncrementalMerkleTreeError::InvalidIndex)
+        ));
+    }
+
+    #[tokio::test]
+    async fn proof_in_a_tree_not_a_power_of_two() {
+        let store = Arc::new(InMemoryStore::new(Some("test")));
+        let hasher = StarkPoseidonHasher::new(Some(false));
+
+        let tree =
+            IncrementalMerkleTree::initialize(6, "0x0".to_string(), hasher.clone(), store, None)
+                .await
+                .unwrap();
+
+        let proof = tree.get_proof(4, "0x0").await.unwrap();
+        assert_eq!(proof.siblings.len(), 3);
+        let root = tree.get_root().await.unwrap();
+        assert!(proof.verify(&hasher, &root, &tree.options()).unwrap());
+        assert!(tree.verify_proof(4, "0x0", &proof.siblings).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn update_many_matches_sequential_updates() {
+        let hasher = StarkPoseidonHasher::new(Some(false));
+        for size in [16, 6] {
+            let batched = IncrementalMerkleTree::initialize(
+                size,
+                "0x0".to_string(),
+                hasher.clone(),
+                Arc::new(InMemoryStore::new(Some("batched"))),
+                None,
+            )
+            .await
+            .unwrap();
+            let sequential = IncrementalMerkleTree::initialize(
+                size,
+                "0x0".to_string(),
+                hasher.clone(),
+                Arc::new(InMemoryStore::new(Some("sequential"))),
+                None,
+            )
+            .await
+            .unwrap();
+
+            let updates = vec![
+                (0, "0x1".to_string()),
+                (1, "0x2".to_string()),
+                (size - 1, "0x3".to_string()),
+                (0, "0x4".to_string()),
+            ];
+            let root = batched.update_many(updates).await.unwrap();
+
+            let mut leaves = vec!["0x0".to_string(); size];
+            for (index, value) in [(1, "0x2"), (size - 1, "0x3"), (0, "0x4")] {
+                let path = sequential.get_inclusion_proof(index).await.unwrap();
+                sequential
+                    .update(index, leaves[index].clone(), value.to_string(), path)
+                    .await
+                    .unwrap();
+                leaves[index] = value.to_string();
+            }
+
+            assert_eq!(root, sequential.get_root().await.unwrap());
+            assert_eq!(root, batched.get_root().await.unwrap());
+            for (index, value) in leaves.iter().enumerate() {
+                let path = batched.get_inclusion_proof(index).await.unwrap();
+                assert!(batched.verify_proof(index, value, &path).await.unwrap());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn update_many_edge_cases() {
+        let store = Arc::new(InMemoryStore::new(Some("test")));
+        let hasher = StarkPoseidonHasher::new(Some(false));
+        let tree =
+            IncrementalMerkleTree::initialize(8, "0x0".to_string(), hasher.clone(), store, None)
+                .await
+                .unwrap();
+        let root = tree.get_root().await.unwrap();
+
+        assert_eq!(tree.update_many(vec![]).await.unwrap(), root);
+        assert!(matches!(
+            tree.update_many(vec![(1, "0x1".to_string()), (8, "0x1".to_string())])
+                .await,
+            Err(IncrementalMerkleTreeError::InvalidIndex)
+        ));
+        assert_eq!(tree.get_root().await.unwrap(), root);
+
+        //? A single leaf is its own root
+        let store = Arc::new(InMemoryStore::new(Some("single")));
+        let tree = IncrementalMerkleTree::initialize(
+            1,
+            "0x0".to_string(),
+            hasher.clone(),
+            store.clone(),
+            None,
+        )
+        .await
+        .unwrap();
+        let root = tree
+            .update_many(vec![(0, "0x1".to_string())])
+            .await
+            .unwrap();
+        assert_eq!(root, "0x1");
+        assert_eq!(tree.get_root().await.unwrap(), root);
+
+        let proof = tree.get_proof(0, "0x1").await.unwrap();
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify(&hasher, &root, &tree.options()).unwrap());
+
+        let reopened = IncrementalMerkleTree::open(store, tree.mmr_id.clone(), hasher)
+            .await
+            .unwrap();
+        assert_eq!(reopened.get_root().await.unwrap(), root);
+    }
+
+    #[tokio::test]
+    async fn lazy_tree_matches_stored_tree() {
+        let hasher = StarkPoseidonHasher::new(Some(false));
+        for (size, node_hashing, domain_separation) in [
+            (16, NodeHashing::Positional, DomainSeparation::Disabled),
+            (6, NodeHashing::Positional, DomainSeparation::Disabled),
+            (11, NodeHashing::Sorted, DomainSeparation::Tagged),
+        ] {
+            let mut trees = Vec::new();
+            for lazy in [false, true] {
+                let tree = IncrementalMerkleTree::initialize_with_options(
+                    size,
+                    "0x0".to_string(),
+                    hasher.clone(),
+                    Arc::new(InMemoryStore::new(Some("test"))),
+                    None,
+                    IncrementalMerkleTreeOptions {
+                        node_hashing,
+                        domain_separation,
+                        lazy,
+                    },
+                )
+                .await
+                .unwrap();
+                trees.push(tree);
+            }
+            let (stored, lazy) = (&trees[0], &trees[1]);
+            assert_eq!(
+                lazy.get_root().await.unwrap(),
+                stored.get_root().await.unwrap()
+            );
+
+            for tree in &trees {
+                let path = tree.get_inclusion_proof(size - 1).await.unwrap();
+                tree.update(size - 1, "0x0".to_string(), "0x9".to_string(), path)
+                    .await
+                    .unwrap();
+                tree.update_many(vec![(2, "0x2".to_string()), (3, "0x3".to_string())])
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(
+                lazy.get_root().await.unwrap(),
+                stored.get_root().await.unwrap()
+            );
+            for index in 0..size {
+                assert_eq!(
+                    lazy.get_inclusion_proof(index).await.unwrap(),
+                    stored.get_inclusion_proof(index).await.unwrap()
+                );
+            }
+
+            let mut indexes = vec![1, 3];
+            let mut values = vec!["0x0".to_string(), "0x3".to_string()];
+            let mut multiproof = lazy
+                .get_inclusion_multi_proof(indexes.clone())
+                .await
+                .unwrap();
+            assert!(lazy
+                .verify_multi_proof(&mut indexes, &mut values, &mut multiproof)
+                .await
+                .unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn lazy_tree_stores_only_non_default_nodes() {
+        let store = Arc::new(InMemoryStore::new(Some("test")));
+        let hasher = StarkPoseidonHasher::new(Some(false));
+        let size = 1 << 32;
+
+        let tree = IncrementalMerkleTree::initialize_with_options(
+            size,
+            "0x0".to_string(),
+            hasher.clone(),
+            store.clone(),
+            None,
+            IncrementalMerkleTreeOptions {
+                lazy: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let empty_root = tree.get_root().await.unwrap();
+        let nodes_prefix = format!("{}:nodes:", tree.mmr_id);
+        let count_nodes = || async {
+            store
+                .scan_prefix(&nodes_prefix)
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap()
+                .len()
+        };
+        assert_eq!(count_nodes().await, 0);
+
+        tree.update_many(vec![(0, "0x1".to_string()), (size - 1, "0x2".to_string())])
+            .await
+            .unwrap();
+        //? Two paths of 32 nodes below the root
+        assert_eq!(count_nodes().await, 64);
+
+        let proof = tree.get_proof(size - 1, "0x2").await.unwrap();
+        assert_eq!(proof.siblings.len(), 32);
+        assert!(proof
+            .verify(&hasher, &tree.get_root().await.unwrap(), &tree.options())
+            .unwrap());
+        let proof = tree.get_proof(12345, "0x0").await.unwrap();
+        assert!(proof
+            .verify(&hasher, &tree.get_root().await.unwrap(), &tree.options())
+            .unwrap());
+
+        tree.update_many(vec![(0, "0x0".to_string()), (size - 1, "0x0".to_string())])
+            .await
+            .unwrap();
+        assert_eq!(tree.get_root().await.unwrap(), empty_root);
+        assert_eq!(count_nodes().await, 0);
+    }
+
+    #[tokio::test]
+    async fn resize_keeps_leaves() {
+        let hasher = StarkPoseidonHasher::new(Some(false));
+        for lazy in [false, true] {
+            let options = IncrementalMerkleTreeOptions {
+                domain_separation: DomainSeparation::Tagged,
+                lazy,
+                ..Default::default()
+            };
+            let store = Arc::new(InMemoryStore::new(Some("test")));
+            let mut tree = IncrementalMerkleTree::initialize_with_options(
+                6,
+                "0x0".to_string(),
+                hasher.clone(),
+                store.clone(),
+                None,
+                options,
+            )
+            .await
+            .unwrap();
+            let leaves = vec![(1, "0x1".to_string()), (5, "0x5".to_string())];
+            tree.update_many(leaves.clone()).await.unwrap();
+
+            for (new_size, grow) in [(13, false), (32, true), (64, true)] {
+                let root = if grow {
+                    tree.grow_depth().await.unwrap()
+                } else {
+                    tree.resize(new_size).await.unwrap()
+                };
+                assert_eq!(tree.size, new_size);
+
+                let expected_store = Arc::new(InMemoryStore::new(Some("expected")));
+                let expected = IncrementalMerkleTree::initialize_with_options(
+                    new_size,
+                    "0x0".to_string(),
+                    hasher.clone(),
+                    expected_store.clone(),
+                    None,
+                    options,
+                )
+                .await
+                .unwrap();
+                assert_eq!(root, expected.update_many(leaves.clone()).await.unwrap());
+                assert_eq!(tree.get_root().await.unwrap(), root);
+
+                //? No node of the old layout is left behind
+                let stored_nodes = |store: Arc<InMemoryStore>, mmr_id: String| async move {
+                    let prefix = format!("{}:nodes:", mmr_id);
+                    let mut nodes: Vec<(String, String)> = store
+                        .scan_prefix(&prefix)
+                        .map_ok(|(key, hash)| (key[prefix.len()..].to_string(), hash))
+                        .try_collect()
+                        .await
+                        .unwrap();
+                    nodes.sort();
+                    nodes
+                };
+                assert_eq!(
+                    stored_nodes(store.clone(), tree.mmr_id.clone()).await,
+                    stored_nodes(expected_store, expected.mmr_id.clone()).await
+                );
+
+                let proof = tree.get_proof(5, "0x5").await.unwrap();
+                assert!(proof.verify(&hasher, &root, &tree.options()).unwrap());
+                let proof = tree.get_proof(new_size - 1, "0x0").await.unwrap();
+                assert!(proof.verify(&hasher, &root, &tree.options()).unwrap());
+            }
+
+            let (size_key, depth_key) =
+                IncrementalMerkleTree::<StarkPoseidonHasher>::get_size_keys(&tree.mmr_id);
+            assert_eq!(store.get(&size_key).await.unwrap(), Some("64".to_string()));
+            assert_eq!(store.get(&depth_key).await.unwrap(), Some("6".to_string()));
+
+            assert!(matches!(
+                tree.resize(63).await,
+                Err(IncrementalMerkleTreeError::InvalidSize)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn open_existing_tree() {
+        let store = Arc::new(InMemoryStore::new(Some("test")));
+        let hasher = StarkPoseidonHasher::new(Some(false));
+        for lazy in [false, true] {
+            let mut tree = IncrementalMerkleTree::initialize_with_options(
+                6,
+                "0x0".to_string(),
+                hasher.clone(),
+                store.clone(),
+                None,
+                IncrementalMerkleTreeOptions {
+                    node_hashing: NodeHashing::Sorted,
+                    domain_separation: DomainSeparation::Tagged,
+                    lazy,
+                },
+            )
+            .await
+            .unwrap();
+            tree.update_many(vec![(2, "0x2".to_string())])
+                .await
+                .unwrap();
+            tree.resize(9).await.unwrap();
+
+            let reopened =
+                IncrementalMerkleTree::open(store.clone(), tree.mmr_id.clone(), hasher.clone())
+                    .await
+                    .unwrap();
+            assert_eq!(reopened.size, 9);
+            assert_eq!(reopened.null_value, "0x0");
+            assert_eq!(reopened.node_hashing, NodeHashing::Sorted);
+            assert_eq!(reopened.domain_separation, DomainSeparation::Tagged);
+            assert_eq!(reopened.lazy, lazy);
+            assert_eq!(
+                reopened.get_root().await.unwrap(),
+                tree.get_root().await.unwrap()
+            );
+
+            let root = reopened
+                .update_many(vec![(8, "0x8".to_string())])
+                .await
+                .unwrap();
+            assert_eq!(
+                root,
+                tree.update_many(vec![(8, "0x8".to_string())])
+                    .await
+                    .unwrap()
+            );
+            let proof = reopened.get_proof(2, "0x2").await.unwrap();
+            assert!(proof.verify(&hasher, &root, &reopened.options()).unwrap());
+
+            assert!(matches!(
+                IncrementalMerkleTree::open(
+                    store.clone(),
+                    tree.mmr_id.clone(),
+                    StarkPedersenHasher::new()
+                )
+                .await,
+                Err(IncrementalMerkleTreeError::InvalidMetadata(_))
+            ));
+        }
+
+        assert!(matches!(
+            IncrementalMerkleTree::open(store.clone(), "unknown".to_string(), hasher.clone()).await,
+            Err(IncrementalMerkleTreeError::TreeNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn open_rejects_an_inconsistent_root() {
+        let store = Arc::new(InMemoryStore::new(Some("test")));
+        let hasher = StarkPoseidonHasher::new(Some(false));
+        let tree = IncrementalMerkleTree::initialize(
+            8,
+            "0x0".to_string(),
+            hasher.clone(),
+            store.clone(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        tree.root_hash.set("0x1234", SubKey::None).await.unwrap();
+        assert!(matches!(
+            IncrementalMerkleTree::open(store, tree.mmr_id.clone(), hasher).await,
+            Err(IncrementalMerkleTreeError::InvalidMetadata(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn open_rejects_missing_or_wrong_metadata() {
+        let hasher = StarkPoseidonHasher::new(Some(false));
+        for lazy in [false, true] {
+            let store = Arc::new(InMemoryStore::new(Some("test")));
+            let tree = IncrementalMerkleTree::initialize_with_options(
+                9,
+                "0x0".to_string(),
+                hasher.clone(),
+                store.clone(),
+                None,
+                IncrementalMerkleTreeOptions {
+                    lazy,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+            tree.update_many(vec![(3, "0x3".to_string())])
+                .await
+                .unwrap();
+            let null_value_key = format!("{}:{:?}", tree.mmr_id, TreeMetadataKeys::NullValue);
+            let hasher_key = format!("{}:{:?}", tree.mmr_id, TreeMetadataKeys::Hasher);
+            let open =
+                || IncrementalMerkleTree::open(store.clone(), tree.mmr_id.clone(), hasher.clone());
+
+            //? The last leaf is hashed with the null value past the end of the leaf level
+            store.set(&null_value_key, "0x1").await.unwrap();
+            assert!(matches!(
+                open().await,
+                Err(IncrementalMerkleTreeError::InvalidMetadata(_))
+            ));
+
+            store.delete(&null_value_key).await.unwrap();
+            assert!(matches!(
+                open().await,
+                Err(IncrementalMerkleTreeError::InvalidMetadata(_))
+            ));
+
+            store.set(&null_value_key, "0x0").await.unwrap();
+            assert!(open().await.is_ok());
+            store.delete(&hasher_key).await.unwrap();
+            assert!(matches!(
+                open().await,
+                Err(IncrementalMerkleTreeError::InvalidMetadata(_))
+            ));
+        }
+    }
+
     #[tokio::test]
     async fn example() {
         use accumulators::{
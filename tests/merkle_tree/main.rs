@@ -1 +1,2 @@
 mod incremental;
+mod static_tree;
@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use accumulators::{
+    hasher::{keccak::KeccakHasher, Hasher},
+    merkle_tree::static_tree::{
+        process_multi_proof, process_proof, StaticMerkleTree, StaticMerkleTreeError,
+        StaticMerkleTreeOptions,
+    },
+};
+
+//? abi.encode(address, uint256) of the values in OpenZeppelin's `StandardMerkleTree` example
+fn encode(address: &str, amount: u128) -> String {
+    format!("0x{:0>64}{:064x}", address, amount)
+}
+
+fn oz_example_leaves() -> Vec<String> {
+    vec![
+        encode(
+            "1111111111111111111111111111111111111111",
+            5_000_000_000_000_000_000,
+        ),
+        encode(
+            "2222222222222222222222222222222222222222",
+            2_500_000_000_000_000_000,
+        ),
+    ]
+}
+
+fn keccak_leaves(count: usize) -> Vec<String> {
+    let hasher = KeccakHasher::new();
+    (0..count)
+        .map(|i| hasher.hash(vec![format!("0x{:064x}", i)]).unwrap())
+        .collect()
+}
+
+#[test]
+fn should_match_openzeppelin_standard_tree() {
+    let tree = StaticMerkleTree::new(
+        oz_example_leaves(),
+        Arc::new(KeccakHasher::new()),
+        StaticMerkleTreeOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        tree.root(),
+        "0xd4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77"
+    );
+    for leaf_index in 0..2 {
+        let proof = tree.get_proof(leaf_index).unwrap();
+        assert_eq!(proof.siblings.len(), 1);
+        assert!(tree.verify_proof(&proof).unwrap());
+    }
+}
+
+#[test]
+fn should_prove_every_leaf() {
+    for options in [
+        StaticMerkleTreeOptions::default(),
+        StaticMerkleTreeOptions {
+            sort_pairs: false,
+            sort_leaves: false,
+            double_hash_leaves: false,
+        },
+    ] {
+        let tree = StaticMerkleTree::new(keccak_leaves(7), Arc::new(KeccakHasher::new()), options)
+            .unwrap();
+
+        for leaf_index in 0..7 {
+            let mut proof = tree.get_proof(leaf_index).unwrap();
+            assert_eq!(proof.leaf_hash, tree.get_leaf_hash(leaf_index).unwrap());
+            assert!(tree.verify_proof(&proof).unwrap());
+            assert_eq!(
+                process_proof(&KeccakHasher::new(), &proof, options.sort_pairs).unwrap(),
+                tree.root()
+            );
+
+            proof.leaf_hash = tree
+                .get_leaf_hash((leaf_index + 1) % 7)
+                .unwrap()
+                .to_string();
+            assert!(!tree.verify_proof(&proof).unwrap());
+        }
+    }
+}
+
+#[test]
+fn should_hash_positionally_without_sorted_pairs() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let leaves = keccak_leaves(2);
+    let tree = StaticMerkleTree::new(
+        leaves.clone(),
+        hasher.clone(),
+        StaticMerkleTreeOptions {
+            sort_pairs: false,
+            sort_leaves: false,
+            double_hash_leaves: false,
+        },
+    )
+    .unwrap();
+
+    //? Leaves are stored from the end of the tree array, so the first one is on the right
+    assert_eq!(
+        tree.root(),
+        hasher
+            .hash(vec![leaves[1].clone(), leaves[0].clone()])
+            .unwrap()
+    );
+}
+
+#[test]
+fn should_verify_multi_proofs() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let tree = StaticMerkleTree::new(
+        keccak_leaves(9),
+        hasher.clone(),
+        StaticMerkleTreeOptions::default(),
+    )
+    .unwrap();
+
+    for leaf_indexes in [vec![], vec![4], vec![0, 1], vec![8, 2, 5], (0..9).collect()] {
+        let multi_proof = tree.get_multi_proof(&leaf_indexes).unwrap();
+        assert_eq!(multi_proof.leaves.len(), leaf_indexes.len());
+        assert!(tree.verify_multi_proof(&multi_proof).unwrap());
+        assert_eq!(
+            process_multi_proof(hasher.as_ref(), &multi_proof).unwrap(),
+            tree.root()
+        );
+    }
+
+    assert!(matches!(
+        tree.get_multi_proof(&[1, 3, 1]),
+        Err(StaticMerkleTreeError::DuplicatedIndex)
+    ));
+
+    let mut multi_proof = tree.get_multi_proof(&[1, 3]).unwrap();
+    multi_proof.leaves.swap(0, 1);
+    assert!(!tree.verify_multi_proof(&multi_proof).unwrap());
+
+    multi_proof.proof_flags.push(true);
+    assert!(matches!(
+        tree.verify_multi_proof(&multi_proof),
+        Err(StaticMerkleTreeError::InvalidMultiProof)
+    ));
+}
+
+#[test]
+fn should_reject_invalid_trees() {
+    let hasher = Arc::new(KeccakHasher::new());
+    assert!(matches!(
+        StaticMerkleTree::new(vec![], hasher.clone(), StaticMerkleTreeOptions::default()),
+        Err(StaticMerkleTreeError::EmptyTree)
+    ));
+
+    let tree = StaticMerkleTree::new(
+        keccak_leaves(3),
+        hasher,
+        StaticMerkleTreeOptions {
+            sort_pairs: false,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(matches!(
+        tree.get_proof(3),
+        Err(StaticMerkleTreeError::InvalidIndex)
+    ));
+    assert!(matches!(
+        tree.get_multi_proof(&[0, 1]),
+        Err(StaticMerkleTreeError::MultiProofRequiresSortedPairs)
+    ));
+}
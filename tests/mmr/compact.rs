@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use accumulators::{
+    hasher::{keccak::KeccakHasher, stark_poseidon::StarkPoseidonHasher},
+    mmr::{compact::CompactMMR, MMRError, PeaksOptions, MMR},
+    store::memory::InMemoryStore,
+};
+
+#[tokio::test]
+async fn should_append_like_full_mmr() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    let mut compact_mmr = CompactMMR::new(hasher.clone());
+
+    for i in 1..=20 {
+        let append = mmr.append(i.to_string()).await.unwrap();
+        assert_eq!(compact_mmr.append(i.to_string()).unwrap(), append);
+    }
+
+    assert_eq!(
+        compact_mmr.peaks,
+        mmr.get_peaks(PeaksOptions {
+            elements_count: None,
+            formatting_opts: None,
+        })
+        .await
+        .unwrap()
+    );
+    assert_eq!(
+        compact_mmr.bag_the_peaks().unwrap(),
+        mmr.bag_the_peaks(None).await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn should_continue_from_full_mmr_peaks() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    for i in 1..=7 {
+        mmr.append(i.to_string()).await.unwrap();
+    }
+
+    let elements_count = mmr.elements_count.get().await.unwrap();
+    let peaks = mmr
+        .get_peaks(PeaksOptions {
+            elements_count: None,
+            formatting_opts: None,
+        })
+        .await
+        .unwrap();
+    let mut compact_mmr = CompactMMR::from_peaks(hasher.clone(), peaks, elements_count).unwrap();
+    assert_eq!(compact_mmr.leaves_count, 7);
+
+    for i in 8..=12 {
+        let append = mmr.append(i.to_string()).await.unwrap();
+        assert_eq!(compact_mmr.append(i.to_string()).unwrap(), append);
+    }
+
+    assert!(matches!(
+        CompactMMR::from_peaks(hasher, vec!["0x1".to_string()], elements_count),
+        Err(MMRError::InvalidPeaksCount)
+    ));
+}
+
+#[tokio::test]
+async fn should_verify_full_mmr_proofs() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    let mut appends = vec![];
+    for i in 1..=11 {
+        appends.push(mmr.append(i.to_string()).await.unwrap());
+    }
+
+    let compact_mmr = CompactMMR::from_mmr(&mmr).await.unwrap();
+
+    for (i, append) in appends.iter().enumerate() {
+        let proof = mmr.get_proof(append.element_index, None).await.unwrap();
+        assert!(compact_mmr
+            .verify_proof(&proof, (i + 1).to_string())
+            .unwrap());
+        assert!(!compact_mmr
+            .verify_proof(&proof, (i + 2).to_string())
+            .unwrap());
+    }
+}
@@ -0,0 +1,229 @@
+//! Conformance of the MMR against vectors checked in under `tests/mmr/vectors`
+//!
+//! Each vector lists the leaves to append and, optionally, what the implementation named in its
+//! `source` returned after each append, its final peaks and some proofs. Any JSON file dropped in
+//! the directory is picked up.
+
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+use accumulators::{
+    hasher::{
+        keccak::KeccakHasher, stark_pedersen::StarkPedersenHasher,
+        stark_poseidon::StarkPoseidonHasher, Hasher, HashingFunction,
+    },
+    mmr::{compact::CompactMMR, PeakBagging, PeaksOptions, MMR},
+    store::memory::InMemoryStore,
+};
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+
+#[derive(Debug, Deserialize)]
+struct Vector {
+    name: String,
+    source: String,
+    hasher: String,
+    #[serde(default)]
+    peak_bagging: Option<String>,
+    #[serde(default)]
+    genesis: bool,
+    appends: Vec<VectorAppend>,
+    #[serde(default)]
+    peaks: Option<Vec<String>>,
+    #[serde(default)]
+    proofs: Vec<VectorProof>,
+}
+
+/// An appended leaf, and what the source returned once it was appended
+#[derive(Debug, Deserialize)]
+struct VectorAppend {
+    value: String,
+    element_index: Option<usize>,
+    leaves_count: Option<usize>,
+    elements_count: Option<usize>,
+    root_hash: Option<String>,
+    bag: Option<String>,
+}
+
+/// A proof against the MMR once every leaf of the vector is appended
+#[derive(Debug, Deserialize)]
+struct VectorProof {
+    element_index: usize,
+    element_value: String,
+    siblings_hashes: Vec<String>,
+}
+
+fn hasher_for(hashing_function: HashingFunction) -> Arc<dyn Hasher> {
+    match hashing_function {
+        HashingFunction::Keccak256 => Arc::new(KeccakHasher::new()),
+        HashingFunction::Poseidon => Arc::new(StarkPoseidonHasher::new(Some(false))),
+        HashingFunction::Pedersen => Arc::new(StarkPedersenHasher::new()),
+    }
+}
+
+fn load_vectors() -> Vec<(PathBuf, Vector)> {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/mmr/vectors");
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let vector = serde_json::from_str(&std::fs::read_to_string(&path).unwrap())
+                .unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+            (path, vector)
+        })
+        .collect()
+}
+
+/// Replays a vector with `hasher` and checks every value it holds
+async fn check_vector(vector: &Vector, hasher: Arc<dyn Hasher>) {
+    let context = format!("{} ({})", vector.name, vector.source);
+    let peak_bagging = match &vector.peak_bagging {
+        Some(peak_bagging) => PeakBagging::from_str(peak_bagging).unwrap(),
+        None => PeakBagging::default(),
+    };
+    let store = Arc::new(InMemoryStore::default());
+    let mut mmr = if vector.genesis {
+        MMR::create_with_genesis(store, hasher, None).await.unwrap()
+    } else {
+        MMR::new(store, hasher, None)
+    }
+    .with_peak_bagging(peak_bagging);
+
+    for expected in &vector.appends {
+        let append = mmr.append(expected.value.clone()).await.unwrap();
+        let context = format!("{}, appending {}", context, expected.value);
+        if let Some(element_index) = expected.element_index {
+            assert_eq!(append.element_index, element_index, "{}", context);
+        }
+        if let Some(leaves_count) = expected.leaves_count {
+            assert_eq!(append.leaves_count, leaves_count, "{}", context);
+        }
+        if let Some(elements_count) = expected.elements_count {
+            assert_eq!(append.elements_count, elements_count, "{}", context);
+        }
+        if let Some(root_hash) = &expected.root_hash {
+            assert_eq!(&append.root_hash, root_hash, "{}", context);
+        }
+        if let Some(bag) = &expected.bag {
+            assert_eq!(&mmr.bag_the_peaks(None).await.unwrap(), bag, "{}", context);
+        }
+    }
+
+    let peaks = mmr
+        .get_peaks(PeaksOptions {
+            elements_count: None,
+            formatting_opts: None,
+        })
+        .await
+        .unwrap();
+    if let Some(expected) = &vector.peaks {
+        assert_eq!(&peaks, expected, "{}", context);
+    }
+
+    for expected in &vector.proofs {
+        let proof = mmr.get_proof(expected.element_index, None).await.unwrap();
+        let context = format!("{}, proving {}", context, expected.element_index);
+        assert_eq!(
+            proof.siblings_hashes, expected.siblings_hashes,
+            "{}",
+            context
+        );
+        assert_eq!(proof.peaks_hashes, peaks, "{}", context);
+        assert!(
+            mmr.verify_proof(proof, expected.element_value.clone(), None)
+                .await
+                .unwrap(),
+            "{}",
+            context
+        );
+    }
+}
+
+/// Checks that appends, roots, peaks and proofs agree with each other and with [`CompactMMR`]
+///
+/// It needs no reference values, so it validates any hasher and peak bagging.
+async fn check_consistency(hasher: Arc<dyn Hasher>, peak_bagging: PeakBagging) {
+    let context = format!("{} with {}", hasher.get_name(), peak_bagging);
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None)
+        .with_peak_bagging(peak_bagging);
+    let mut compact = CompactMMR::new(hasher.clone()).with_peak_bagging(peak_bagging);
+
+    let mut appends = Vec::new();
+    for leaf in 1..=11 {
+        let value = format!("0x{:x}", leaf);
+        let append = mmr.append(value.clone()).await.unwrap();
+        assert_eq!(
+            compact.append(value.clone()).unwrap(),
+            append,
+            "{}",
+            context
+        );
+
+        let peaks = mmr
+            .get_peaks(PeaksOptions {
+                elements_count: None,
+                formatting_opts: None,
+            })
+            .await
+            .unwrap();
+        let bag = peak_bagging.bag(hasher.as_ref(), peaks).unwrap();
+        let root_hash = peak_bagging
+            .root(hasher.as_ref(), &bag, append.elements_count)
+            .unwrap();
+        assert_eq!(append.root_hash, root_hash, "{}", context);
+        appends.push((value, append));
+    }
+
+    for (value, append) in appends {
+        let proof = mmr.get_proof(append.element_index, None).await.unwrap();
+        assert!(
+            compact.verify_proof(&proof, value.clone()).unwrap(),
+            "{}",
+            context
+        );
+        assert!(
+            !compact.verify_proof(&proof, "0xdead".to_string()).unwrap(),
+            "{}",
+            context
+        );
+        assert!(
+            mmr.verify_proof(proof, value, None).await.unwrap(),
+            "{}",
+            context
+        );
+    }
+}
+
+#[tokio::test]
+async fn should_match_checked_in_vectors() {
+    let vectors = load_vectors();
+    assert!(!vectors.is_empty());
+
+    for (path, vector) in vectors {
+        let hashing_function = HashingFunction::from_str(&vector.hasher)
+            .unwrap_or_else(|_| panic!("{}: unknown hasher", path.display()));
+        check_vector(&vector, hasher_for(hashing_function)).await;
+    }
+}
+
+#[tokio::test]
+async fn should_be_consistent_for_every_hasher_and_peak_bagging() {
+    for hashing_function in HashingFunction::iter() {
+        for peak_bagging in [
+            PeakBagging::RightToLeft,
+            PeakBagging::LeftToRight,
+            PeakBagging::RightToLeftWithoutSize,
+            PeakBagging::Flat,
+        ] {
+            check_consistency(hasher_for(hashing_function), peak_bagging).await;
+        }
+    }
+}
@@ -3,10 +3,10 @@ use std::sync::Arc;
 use accumulators::{
     hasher::{
         keccak::KeccakHasher, stark_pedersen::StarkPedersenHasher,
-        stark_poseidon::StarkPoseidonHasher, Hasher,
+        stark_poseidon::StarkPoseidonHasher, DomainSeparation, Hasher, NodeHashing,
     },
-    mmr::{AppendResult, PeaksOptions, Proof, ProofOptions, MMR},
-    store::{memory::InMemoryStore, sqlite::SQLiteStore, SubKey},
+    mmr::{compact::CompactMMR, AppendResult, MMRError, PeaksOptions, Proof, ProofOptions, MMR},
+    store::{memory::InMemoryStore, sqlite::SQLiteStore, Store, SubKey},
 };
 
 const LEAVES: [&str; 5] = ["1", "2", "3", "4", "5"];
@@ -811,3 +811,392 @@ async fn timestamp_remappers_test() {
     let correct_root_hash = "0x32f5a2949cac3d06e854701c5a2a00ed51c0475a31c1bc17cc6d3ec46425e9";
     assert_eq!(correct_root_hash, root_hash);
 }
+
+//================================================================================================
+// Tests for updating proofs
+//================================================================================================
+
+#[tokio::test]
+async fn should_update_proof_to_bigger_mmr() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+
+    let mut appends = vec![];
+    for i in 1..=5 {
+        appends.push(mmr.append(i.to_string()).await.unwrap());
+    }
+    let old_elements_count = mmr.elements_count.get().await.unwrap();
+    let old_proofs = mmr
+        .get_proofs(appends.iter().map(|a| a.element_index).collect(), None)
+        .await
+        .unwrap();
+
+    for i in 6..=13 {
+        mmr.append(i.to_string()).await.unwrap();
+    }
+    let new_elements_count = mmr.elements_count.get().await.unwrap();
+
+    let new_nodes = mmr
+        .hashes
+        .get_many(
+            (old_elements_count + 1..=new_elements_count)
+                .map(SubKey::Usize)
+                .collect(),
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(idx, hash)| (idx.parse::<usize>().unwrap(), hash))
+        .collect();
+
+    for (i, old_proof) in old_proofs.iter().enumerate() {
+        let proof = old_proof.update(new_elements_count, &new_nodes).unwrap();
+        assert_eq!(
+            proof,
+            mmr.get_proof(old_proof.element_index, None).await.unwrap()
+        );
+        assert!(mmr
+            .verify_proof(proof, (i + 1).to_string(), None)
+            .await
+            .unwrap());
+    }
+}
+
+#[tokio::test]
+async fn should_update_proof_with_only_required_nodes() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+
+    for i in 1..=3 {
+        mmr.append(i.to_string()).await.unwrap();
+    }
+    let old_proof = mmr.get_proof(4, None).await.unwrap();
+
+    for i in 4..=9 {
+        mmr.append(i.to_string()).await.unwrap();
+    }
+    let new_elements_count = mmr.elements_count.get().await.unwrap();
+
+    let required = old_proof
+        .required_nodes_for_update(new_elements_count)
+        .unwrap();
+    assert!(required.iter().all(|&idx| idx > old_proof.elements_count));
+
+    let mut new_nodes = std::collections::HashMap::new();
+    for idx in required {
+        let hash = mmr.hashes.get(SubKey::Usize(idx)).await.unwrap().unwrap();
+        new_nodes.insert(idx, hash);
+    }
+
+    let proof = old_proof.update(new_elements_count, &new_nodes).unwrap();
+    assert!(mmr
+        .verify_proof(proof, "3".to_string(), None)
+        .await
+        .unwrap());
+
+    assert!(old_proof
+        .update(new_elements_count, &std::collections::HashMap::new())
+        .is_err());
+}
+
+#[tokio::test]
+async fn should_list_and_delete_mmrs() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut first = MMR::new(store.clone(), hasher.clone(), Some("first".to_string()));
+    let mut second = MMR::new(store.clone(), hasher.clone(), Some("first-2".to_string()));
+    for leaf in LEAVES {
+        first.append(leaf.to_string()).await.unwrap();
+        second.append(leaf.to_string()).await.unwrap();
+    }
+    store.set("unrelated", "value").await.unwrap();
+
+    assert_eq!(
+        MMR::list_ids(store.clone()).await.unwrap(),
+        vec!["first".to_string(), "first-2".to_string()]
+    );
+
+    first.delete().await.unwrap();
+    assert_eq!(
+        MMR::list_ids(store.clone()).await.unwrap(),
+        vec!["first-2".to_string()]
+    );
+    assert!(store
+        .store
+        .read()
+        .keys()
+        .all(|key| !key.starts_with("first:")));
+    assert_eq!(second.leaves_count.get().await.unwrap(), LEAVES.len());
+    assert_eq!(
+        store.get("unrelated").await.unwrap(),
+        Some("value".to_string())
+    );
+}
+
+//================================================================================================
+// Tests for node hashing
+//================================================================================================
+
+#[tokio::test]
+async fn should_prove_with_sorted_node_hashing() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut positional = MMR::new(store.clone(), hasher.clone(), None);
+    let mut sorted =
+        MMR::new(store.clone(), hasher.clone(), None).with_node_hashing(NodeHashing::Sorted);
+    let mut appends = vec![];
+    for i in 1..=11 {
+        positional.append(i.to_string()).await.unwrap();
+        appends.push(sorted.append(i.to_string()).await.unwrap());
+    }
+    assert_ne!(
+        positional.root_hash.get(SubKey::None).await.unwrap(),
+        sorted.root_hash.get(SubKey::None).await.unwrap()
+    );
+
+    let compact = CompactMMR::from_mmr(&sorted).await.unwrap();
+    assert_eq!(compact.node_hashing, NodeHashing::Sorted);
+    assert_eq!(
+        Some(compact.root_hash().unwrap()),
+        sorted.root_hash.get(SubKey::None).await.unwrap()
+    );
+
+    for (leaf, append) in appends.iter().enumerate() {
+        let value = (leaf + 1).to_string();
+        let proof = sorted.get_proof(append.element_index, None).await.unwrap();
+        assert!(sorted
+            .verify_proof(proof.clone(), value.clone(), None)
+            .await
+            .unwrap());
+        assert!(compact.verify_proof(&proof, value.clone()).unwrap());
+
+        //? A sorted verifier reaches the peak without knowing the element position
+        let peak = proof
+            .siblings_hashes
+            .iter()
+            .try_fold(value, |hash, sibling| {
+                NodeHashing::Sorted.hash_pair(hasher.as_ref(), hash, sibling.clone())
+            })
+            .unwrap();
+        assert!(proof.peaks_hashes.contains(&peak));
+    }
+}
+
+#[tokio::test]
+async fn should_sort_decimal_and_hex_leaves_by_value() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+
+    let mut mmr = MMR::new(store, hasher.clone(), None).with_node_hashing(NodeHashing::Sorted);
+    mmr.append("10".to_string()).await.unwrap();
+    mmr.append("0xf".to_string()).await.unwrap();
+
+    //? 10 is smaller than 0xf, so it is hashed first
+    let parent = hasher
+        .hash(vec!["10".to_string(), "0xf".to_string()])
+        .unwrap();
+    assert_eq!(
+        mmr.root_hash.get(SubKey::None).await.unwrap(),
+        Some(hasher.hash(vec!["3".to_string(), parent]).unwrap())
+    );
+}
+
+#[tokio::test]
+async fn should_prove_with_domain_separation() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut mmr = MMR::new(store.clone(), hasher.clone(), None)
+        .with_node_hashing(NodeHashing::Sorted)
+        .with_domain_separation(DomainSeparation::Tagged);
+    let mut appends = vec![];
+    for i in 1..=11 {
+        appends.push(mmr.append(i.to_string()).await.unwrap());
+    }
+
+    //? Leaves are stored tagged
+    let (leaf_tag, _) = hasher.get_domain_tags();
+    assert_eq!(
+        mmr.hashes.get(SubKey::Usize(1)).await.unwrap(),
+        Some(hasher.hash(vec![leaf_tag, "1".to_string()]).unwrap())
+    );
+
+    let reopened = MMR::open(store.clone(), hasher.clone(), mmr.mmr_id.clone())
+        .await
+        .unwrap();
+    assert_eq!(reopened.node_hashing, NodeHashing::Sorted);
+    assert_eq!(reopened.domain_separation, DomainSeparation::Tagged);
+
+    let compact = CompactMMR::from_mmr(&mmr).await.unwrap();
+    assert_eq!(
+        Some(compact.root_hash().unwrap()),
+        mmr.root_hash.get(SubKey::None).await.unwrap()
+    );
+
+    let untagged = MMR::new(store, hasher, Some(mmr.mmr_id.clone()));
+    for (leaf, append) in appends.iter().enumerate() {
+        let value = (leaf + 1).to_string();
+        let proof = reopened
+            .get_proof(append.element_index, None)
+            .await
+            .unwrap();
+        assert!(reopened
+            .verify_proof(proof.clone(), value.clone(), None)
+            .await
+            .unwrap());
+        assert!(compact.verify_proof(&proof, value.clone()).unwrap());
+        assert!(!untagged.verify_proof(proof, value, None).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn should_reject_appending_with_other_hashing_settings() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut mmr = MMR::new(store.clone(), hasher.clone(), None)
+        .with_domain_separation(DomainSeparation::Tagged);
+    mmr.append("1".to_string()).await.unwrap();
+    let root_hash = mmr.root_hash.get(SubKey::None).await.unwrap();
+
+    let mut untagged = MMR::new(store.clone(), hasher.clone(), Some(mmr.mmr_id.clone()));
+    assert!(matches!(
+        untagged.append("2".to_string()).await,
+        Err(MMRError::HashingMetadataMismatch(_))
+    ));
+    let mut sorted = MMR::new(store.clone(), hasher.clone(), Some(mmr.mmr_id.clone()))
+        .with_domain_separation(DomainSeparation::Tagged)
+        .with_node_hashing(NodeHashing::Sorted);
+    assert!(matches!(
+        sorted.append("2".to_string()).await,
+        Err(MMRError::HashingMetadataMismatch(_))
+    ));
+    assert_eq!(mmr.root_hash.get(SubKey::None).await.unwrap(), root_hash);
+    assert_eq!(mmr.elements_count.get().await.unwrap(), 1);
+
+    let mut reopened = MMR::open(store, hasher, mmr.mmr_id.clone()).await.unwrap();
+    reopened.append("2".to_string()).await.unwrap();
+}
+
+//================================================================================================
+// Tests for append_data
+//================================================================================================
+
+#[tokio::test]
+async fn should_append_and_prove_raw_data() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(KeccakHasher::new());
+    let mut mmr = MMR::new(store, hasher.clone(), None);
+
+    let payloads: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i; 100 + i as usize]).collect();
+    let mut appends = vec![];
+    for (i, payload) in payloads.iter().enumerate() {
+        appends.push(mmr.append_data(payload, i % 2 == 0).await.unwrap());
+    }
+
+    assert_eq!(
+        mmr.hashes
+            .get(SubKey::Usize(appends[1].element_index))
+            .await
+            .unwrap(),
+        Some(hasher.hash_data(&payloads[1]).unwrap())
+    );
+    assert_eq!(
+        mmr.get_preimage(appends[0].element_index).await.unwrap(),
+        Some(payloads[0].clone())
+    );
+    assert_eq!(
+        mmr.get_preimage(appends[1].element_index).await.unwrap(),
+        None
+    );
+
+    for (payload, append) in payloads.iter().zip(&appends) {
+        let proof = mmr.get_proof(append.element_index, None).await.unwrap();
+        assert!(mmr
+            .verify_proof_data(proof.clone(), payload, None)
+            .await
+            .unwrap());
+        assert!(!mmr.verify_proof_data(proof, &[0], None).await.unwrap());
+    }
+}
+
+//================================================================================================
+// Tests for typed MMRs
+//================================================================================================
+
+#[tokio::test]
+async fn typed_mmr_matches_dyn_mmr() {
+    let store = Arc::new(InMemoryStore::default());
+    let mut typed: MMR<KeccakHasher, InMemoryStore> =
+        MMR::new_typed(store.clone(), Arc::new(KeccakHasher::new()), None)
+            .with_node_hashing(NodeHashing::Sorted);
+    let mut dynamic = MMR::new(
+        Arc::new(InMemoryStore::default()),
+        Arc::new(KeccakHasher::new()),
+        None,
+    )
+    .with_node_hashing(NodeHashing::Sorted);
+
+    for value in LEAVES {
+        assert_eq!(
+            typed.append(value.to_string()).await.unwrap().root_hash,
+            dynamic.append(value.to_string()).await.unwrap().root_hash
+        );
+    }
+
+    let proof = typed.get_proof(4, None).await.unwrap();
+    assert_eq!(proof, dynamic.get_proof(4, None).await.unwrap());
+    assert!(typed
+        .verify_proof(proof.clone(), "3".to_string(), None)
+        .await
+        .unwrap());
+
+    let reopened: MMR<KeccakHasher, InMemoryStore> =
+        MMR::open_typed(store, typed.hasher.clone(), typed.mmr_id.clone())
+            .await
+            .unwrap();
+    assert_eq!(reopened.node_hashing, NodeHashing::Sorted);
+
+    let bag = typed.bag_the_peaks(None).await.unwrap();
+    assert_eq!(typed.into_dyn().bag_the_peaks(None).await.unwrap(), bag);
+}
+
+#[tokio::test]
+async fn typed_mmr_builds_the_other_mmr_types() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mut typed: MMR<KeccakHasher, InMemoryStore> =
+        MMR::new_typed(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    let mut dynamic = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    for value in LEAVES {
+        typed.append(value.to_string()).await.unwrap();
+        dynamic.append(value.to_string()).await.unwrap();
+    }
+
+    let proof = typed.get_proof(4, None).await.unwrap();
+    let compact: CompactMMR<KeccakHasher> = CompactMMR::from_mmr(&typed).await.unwrap();
+    assert!(compact.verify_proof(&proof, "3".to_string()).unwrap());
+
+    let mut snapshot = Vec::new();
+    typed.export_snapshot(&mut snapshot).await.unwrap();
+    let imported: MMR<KeccakHasher, InMemoryStore> = MMR::import_snapshot_typed(
+        snapshot.as_slice(),
+        Arc::new(InMemoryStore::default()),
+        hasher.clone(),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        imported.root_hash.get(SubKey::None).await.unwrap(),
+        typed.root_hash.get(SubKey::None).await.unwrap()
+    );
+
+    let mut draft = typed.start_draft().await.unwrap();
+    let draft_root = draft.mmr.append("6".to_string()).await.unwrap().root_hash;
+    draft.commit().await.unwrap();
+    let root = dynamic.append("6".to_string()).await.unwrap().root_hash;
+    assert_eq!(draft_root, root);
+    assert_eq!(typed.root_hash.get(SubKey::None).await.unwrap(), Some(root));
+}
@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
 use accumulators::{
-    hasher::stark_poseidon::StarkPoseidonHasher, mmr::MMR, store::memory::InMemoryStore,
+    hasher::stark_poseidon::StarkPoseidonHasher,
+    mmr::{draft::DraftDiff, PeaksOptions, MMR},
+    store::{memory::InMemoryStore, SubKey},
 };
 
 #[tokio::test]
@@ -191,3 +193,133 @@ async fn example() {
 
     assert_eq!(after_discard_root, root);
 }
+
+#[tokio::test]
+async fn should_diff_and_prove_against_draft() {
+    let store = InMemoryStore::default();
+    let store = Arc::new(store);
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut mmr = MMR::new(store.clone(), hasher.clone(), None);
+    mmr.append("1".to_string()).await.expect("Failed to append");
+    mmr.append("2".to_string()).await.expect("Failed to append");
+    mmr.append("3".to_string()).await.expect("Failed to append");
+    let old_peaks = mmr
+        .get_peaks(PeaksOptions {
+            elements_count: None,
+            formatting_opts: None,
+        })
+        .await
+        .unwrap();
+    let old_root = mmr
+        .calculate_root_hash(&mmr.bag_the_peaks(None).await.unwrap(), 4)
+        .unwrap();
+
+    let mut draft = mmr.start_draft().await.unwrap();
+    let eg_value = "4".to_string();
+    let eg_append = draft
+        .mmr
+        .append(eg_value.clone())
+        .await
+        .expect("Failed to append");
+
+    let diff = draft.diff().await.unwrap();
+    assert_eq!(diff.old_elements_count, 4);
+    assert_eq!(diff.new_elements_count, 7);
+    assert_eq!(diff.old_leaves_count, 3);
+    assert_eq!(diff.new_leaves_count, 4);
+    assert_eq!(diff.leaves, vec![(5, eg_value.clone())]);
+    assert_eq!(
+        diff.nodes.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(),
+        vec![6, 7]
+    );
+    assert_eq!(diff.old_peaks, old_peaks);
+    assert_eq!(diff.new_peaks, vec![diff.nodes[1].1.clone()]);
+    assert_eq!(diff.old_root_hash, old_root);
+    assert_eq!(diff.new_root_hash, eg_append.root_hash);
+
+    let proof = draft
+        .mmr
+        .get_proof(eg_append.element_index, None)
+        .await
+        .expect("Failed to get proof");
+    assert!(draft
+        .mmr
+        .verify_proof(proof, eg_value, None)
+        .await
+        .expect("Failed to verify proof"));
+}
+
+#[tokio::test]
+async fn should_apply_exported_diff_to_another_copy() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    let mut copy = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    for value in ["1", "2", "3", "4", "5"] {
+        mmr.append(value.to_string())
+            .await
+            .expect("Failed to append");
+        copy.append(value.to_string())
+            .await
+            .expect("Failed to append");
+    }
+
+    let mut draft = mmr.start_draft().await.unwrap();
+    for value in ["6", "7", "8"] {
+        draft
+            .mmr
+            .append(value.to_string())
+            .await
+            .expect("Failed to append");
+    }
+    let exported = draft.export_diff().await.unwrap();
+    draft.commit().await.unwrap();
+
+    let diff = DraftDiff::from_json(&exported).unwrap();
+    copy.apply_diff(&diff).await.unwrap();
+
+    assert_eq!(
+        copy.elements_count.get().await.unwrap(),
+        mmr.elements_count.get().await.unwrap()
+    );
+    assert_eq!(
+        copy.root_hash.get(SubKey::None).await.unwrap(),
+        mmr.root_hash.get(SubKey::None).await.unwrap()
+    );
+    assert_eq!(
+        copy.root_hash.get(SubKey::None).await.unwrap(),
+        Some(diff.new_root_hash.clone())
+    );
+
+    //? The same diff cannot be applied twice
+    assert!(copy.apply_diff(&diff).await.is_err());
+}
+
+#[tokio::test]
+async fn should_reject_tampered_diff() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    let mut copy = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    for value in ["1", "2", "3"] {
+        mmr.append(value.to_string())
+            .await
+            .expect("Failed to append");
+        copy.append(value.to_string())
+            .await
+            .expect("Failed to append");
+    }
+
+    let mut draft = mmr.start_draft().await.unwrap();
+    draft
+        .mmr
+        .append("4".to_string())
+        .await
+        .expect("Failed to append");
+    let mut diff = draft.diff().await.unwrap();
+    diff.nodes[0].1 = "0x1".to_string();
+
+    assert!(copy.apply_diff(&diff).await.is_err());
+    assert_eq!(copy.elements_count.get().await.unwrap(), 4);
+}
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use accumulators::{
+    hasher::{
+        keccak::KeccakHasher, stark_pedersen::StarkPedersenHasher,
+        stark_poseidon::StarkPoseidonHasher, Hasher, HashingFunction,
+    },
+    mmr::{format_proof_cairo, CairoEncoding, FormattingError, Proof, MMR},
+    store::memory::InMemoryStore,
+};
+
+/// Appends the leaves 1, 2, 4, 5 and 8, and formats the proof of the first one
+async fn format_first_proof(hasher: Arc<dyn Hasher>) -> Vec<String> {
+    let hashing_function = hasher.get_name();
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher, None);
+    for value in ["1", "2", "4", "5", "8"] {
+        mmr.append(value.to_string()).await.unwrap();
+    }
+    let proof = mmr.get_proof(1, None).await.unwrap();
+    format_proof_cairo(
+        &proof,
+        hashing_function,
+        CairoEncoding::for_hashing_function(hashing_function),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn should_format_proof_as_cairo_felts() {
+    //? The nodes are computed with the Poseidon and Pedersen of starknet-types-core 0.1.5, the
+    //? builtins of the Cairo VM: `7 = hash(hash(1, 2), hash(4, 5))` and `6 = hash(4, 5)`.
+    //? cairo-lib itself is not run, so this pins the layout and hashes, not its `verify_proof`.
+    assert_eq!(
+        format_first_proof(Arc::new(StarkPoseidonHasher::new(Some(false)))).await,
+        vec![
+            "0x1",
+            "0x1",
+            "0x2",
+            "0x43c59debacab61e73dec9edd73da27738a8be14c1e123bb38f9634220323c4f",
+            "0x8",
+            "0x2",
+            "0x2",
+            "0x384f427301be8e1113e6dd91088cb46e25a8f6426a997b2f842a39596bf45f4",
+        ]
+    );
+    assert_eq!(
+        format_first_proof(Arc::new(StarkPedersenHasher::new())).await,
+        vec![
+            "0x1",
+            "0x1",
+            "0x2",
+            "0x6a1ae86c82c8e96a8bd73f70c5b729ebdd947a34cb50f6b1b3cebaad0eb13a0",
+            "0x8",
+            "0x2",
+            "0x2",
+            "0x57166f9476d0a2d6875124251841eb85a9ae37462fae3cbf7304bcd593938e7",
+        ]
+    );
+
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher, None);
+    for value in ["1", "2", "4", "5", "8"] {
+        mmr.append(value.to_string()).await.unwrap();
+    }
+
+    //? A single leaf MMR has no siblings
+    let proof = mmr.get_proof(8, None).await.unwrap();
+    let felts = format_proof_cairo(&proof, HashingFunction::Poseidon, CairoEncoding::Felt).unwrap();
+    assert_eq!(felts[..2], ["0x8", "0x8"]);
+    assert_eq!(felts.last().unwrap(), "0x0");
+}
+
+#[tokio::test]
+async fn should_split_keccak_hashes_into_uint256() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher, None);
+    for leaf in 1..=3 {
+        mmr.append(format!("0x{:064x}", leaf)).await.unwrap();
+    }
+
+    let proof = mmr.get_proof(1, None).await.unwrap();
+    let felts =
+        format_proof_cairo(&proof, HashingFunction::Keccak256, CairoEncoding::Uint256).unwrap();
+
+    //? Low then high 128 bits. The peak `hash(1, 2)` is the root ckb-merkle-mountain-range
+    //? returns for those leaves with a sha3 Keccak merge.
+    assert_eq!(
+        felts,
+        vec![
+            "0x1",
+            "0x1",
+            "0x0",
+            "0x2",
+            "0xc83a08bbccc01a0644d599ccd2a7c2e0",
+            "0xe90b7bceb6e7df5418fb78d8ee546e97",
+            "0x3",
+            "0x0",
+            "0x1",
+            "0x2",
+            "0x0",
+        ]
+    );
+}
+
+#[test]
+fn should_reject_values_outside_the_cairo_encoding() {
+    let proof = |element_hash: &str| Proof {
+        element_index: 1,
+        element_hash: element_hash.to_string(),
+        siblings_hashes: vec![],
+        peaks_hashes: vec![element_hash.to_string()],
+        elements_count: 1,
+    };
+    let max_u256 = format!("0x{}", "f".repeat(64));
+
+    //? Above the field prime, but still a valid u256
+    assert!(matches!(
+        format_proof_cairo(
+            &proof(&max_u256),
+            HashingFunction::Poseidon,
+            CairoEncoding::Felt
+        ),
+        Err(FormattingError::CairoValueError(_))
+    ));
+    assert_eq!(
+        format_proof_cairo(
+            &proof(&max_u256),
+            HashingFunction::Keccak256,
+            CairoEncoding::Uint256
+        )
+        .unwrap()[1..3],
+        [
+            format!("0x{}", "f".repeat(32)),
+            format!("0x{}", "f".repeat(32))
+        ]
+    );
+
+    for value in [format!("0x1{}", "0".repeat(64)), "0xzz".to_string()] {
+        assert!(matches!(
+            format_proof_cairo(
+                &proof(&value),
+                HashingFunction::Keccak256,
+                CairoEncoding::Uint256
+            ),
+            Err(FormattingError::CairoValueError(_))
+        ));
+    }
+}
+
+#[test]
+fn should_reject_the_encoding_of_another_hasher() {
+    let proof = Proof {
+        element_index: 1,
+        element_hash: "0x1".to_string(),
+        siblings_hashes: vec![],
+        peaks_hashes: vec!["0x1".to_string()],
+        elements_count: 1,
+    };
+
+    for (hashing_function, encoding) in [
+        (HashingFunction::Keccak256, CairoEncoding::Felt),
+        (HashingFunction::Poseidon, CairoEncoding::Uint256),
+        (HashingFunction::Pedersen, CairoEncoding::Uint256),
+    ] {
+        assert!(matches!(
+            format_proof_cairo(&proof, hashing_function, encoding),
+            Err(FormattingError::CairoEncodingMismatch { .. })
+        ));
+    }
+}
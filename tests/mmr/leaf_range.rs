@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use accumulators::{
+    hasher::{
+        keccak::KeccakHasher, stark_poseidon::StarkPoseidonHasher, DomainSeparation, NodeHashing,
+    },
+    mmr::{
+        map_leaf_index_to_element_index, HashingOptions, LeafRangeProof, MMRError, PeakBagging, MMR,
+    },
+    store::memory::InMemoryStore,
+};
+
+async fn setup(mut mmr: MMR, leaves: usize) -> (MMR, Vec<String>, String) {
+    let values: Vec<String> = (0..leaves).map(|i| format!("0x{:x}", i + 1)).collect();
+    let mut root_hash = String::new();
+    for value in &values {
+        root_hash = mmr.append(value.clone()).await.unwrap().root_hash;
+    }
+    (mmr, values, root_hash)
+}
+
+#[tokio::test]
+async fn should_prove_leaf_ranges() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    //? 1000 leaves make mountains of 512, 256, 128, 64, 32 and 8 leaves
+    let (mmr, values, root_hash) = setup(mmr, 1000).await;
+
+    for (first, last) in [
+        (100, 399),
+        (500, 700),
+        (0, 999),
+        (999, 999),
+        (0, 0),
+        (992, 999),
+    ] {
+        let proof = mmr.get_leaf_range_proof(first, last, None).await.unwrap();
+        assert!(proof
+            .verify(
+                hasher.as_ref(),
+                &values[first..=last],
+                &root_hash,
+                &mmr.hashing_options()
+            )
+            .unwrap());
+
+        let mut tampered = values[first..=last].to_vec();
+        tampered[0] = "0x12345".to_string();
+        assert!(!proof
+            .verify(
+                hasher.as_ref(),
+                &tampered,
+                &root_hash,
+                &mmr.hashing_options()
+            )
+            .unwrap());
+        assert!(!proof
+            .verify(
+                hasher.as_ref(),
+                &values[first..last],
+                &root_hash,
+                &mmr.hashing_options()
+            )
+            .unwrap());
+    }
+
+    assert!(matches!(
+        mmr.get_leaf_range_proof(10, 1000, None).await,
+        Err(MMRError::InvalidElementIndex)
+    ));
+}
+
+#[tokio::test]
+async fn should_be_smaller_than_individual_proofs() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    let (mmr, _, _) = setup(mmr, 1000).await;
+
+    let proof = mmr.get_leaf_range_proof(100, 399, None).await.unwrap();
+    let individual_proofs = mmr
+        .get_proofs(
+            (100..=399).map(map_leaf_index_to_element_index).collect(),
+            None,
+        )
+        .await
+        .unwrap();
+    let individual_siblings: usize = individual_proofs
+        .iter()
+        .map(|proof| proof.siblings_hashes.len())
+        .sum();
+
+    //? Only the borders of 100..=399 inside the 512 leaves mountain are needed
+    assert!(proof.border_hashes.len() <= 2 * 9);
+    assert!(proof.border_hashes.len() * 100 < individual_siblings);
+}
+
+#[tokio::test]
+async fn should_prove_leaf_ranges_with_tagged_sorted_hashing() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None)
+        .with_node_hashing(NodeHashing::Sorted)
+        .with_domain_separation(DomainSeparation::Tagged);
+    let (mmr, values, root_hash) = setup(mmr, 37).await;
+
+    let proof = mmr.get_leaf_range_proof(3, 33, None).await.unwrap();
+    assert!(proof
+        .verify(
+            hasher.as_ref(),
+            &values[3..=33],
+            &root_hash,
+            &mmr.hashing_options()
+        )
+        .unwrap());
+
+    let mut untagged = proof.clone();
+    untagged.domain_separation = DomainSeparation::Disabled;
+    assert!(!untagged
+        .verify(
+            hasher.as_ref(),
+            &values[3..=33],
+            &root_hash,
+            &mmr.hashing_options()
+        )
+        .unwrap());
+}
+
+#[tokio::test]
+async fn should_reject_proofs_with_forged_settings() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None)
+        .with_domain_separation(DomainSeparation::Tagged);
+    let (mmr, _, root_hash) = setup(mmr, 10).await;
+
+    //? The root posing as the single leaf and peak of an untagged MMR bagged without its size
+    let forged = LeafRangeProof {
+        first_leaf_index: 0,
+        leaves_count: 1,
+        border_hashes: Default::default(),
+        peaks_hashes: vec![root_hash.clone()],
+        elements_count: 1,
+        node_hashing: mmr.node_hashing,
+        domain_separation: DomainSeparation::Disabled,
+    };
+    let leaf = [root_hash.clone()];
+    let untagged_without_size = HashingOptions {
+        domain_separation: DomainSeparation::Disabled,
+        peak_bagging: PeakBagging::RightToLeftWithoutSize,
+        ..mmr.hashing_options()
+    };
+    assert!(forged
+        .verify(hasher.as_ref(), &leaf, &root_hash, &untagged_without_size)
+        .unwrap());
+    //? The proof cannot switch tagging off
+    assert!(!forged
+        .verify(hasher.as_ref(), &leaf, &root_hash, &mmr.hashing_options())
+        .unwrap());
+
+    //? Claiming the trusted settings, the leaf is tagged and bagged with the size
+    let forged = LeafRangeProof {
+        domain_separation: mmr.domain_separation,
+        ..forged
+    };
+    assert!(!forged
+        .verify(hasher.as_ref(), &leaf, &root_hash, &mmr.hashing_options())
+        .unwrap());
+}
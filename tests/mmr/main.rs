@@ -1,4 +1,14 @@
+mod compact;
+mod conformance;
 mod core;
 mod draft;
+mod formatting;
 mod helpers;
+mod leaf_range;
+mod peak_bagging;
+mod pruning;
+mod sharded;
+mod shared;
+mod snapshot;
+mod sorted;
 mod stacked;
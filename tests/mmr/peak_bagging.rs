@@ -0,0 +1,201 @@
+use std::{str::FromStr, sync::Arc};
+
+use accumulators::{
+    hasher::{keccak::KeccakHasher, Hasher},
+    mmr::{compact::CompactMMR, HashingOptions, MMRError, PeakBagging, MMR},
+    store::memory::InMemoryStore,
+};
+
+const POLICIES: [PeakBagging; 4] = [
+    PeakBagging::RightToLeft,
+    PeakBagging::LeftToRight,
+    PeakBagging::RightToLeftWithoutSize,
+    PeakBagging::Flat,
+];
+
+async fn setup(peak_bagging: PeakBagging, leaves: usize) -> (MMR, Vec<String>, String) {
+    let store = Arc::new(InMemoryStore::default());
+    let mut mmr =
+        MMR::new(store, Arc::new(KeccakHasher::new()), None).with_peak_bagging(peak_bagging);
+    let values: Vec<String> = (0..leaves).map(|i| format!("0x{:x}", i + 1)).collect();
+    let mut root_hash = String::new();
+    for value in &values {
+        root_hash = mmr.append(value.clone()).await.unwrap().root_hash;
+    }
+    (mmr, values, root_hash)
+}
+
+#[tokio::test]
+async fn should_bag_as_documented() {
+    let hasher = KeccakHasher::new();
+    let hash = |data: &[&str]| {
+        hasher
+            .hash(data.iter().map(|s| s.to_string()).collect())
+            .unwrap()
+    };
+
+    //? 7 leaves make peaks of 4, 2 and 1 leaves, over 11 elements
+    let p1 = hash(&[&hash(&["0x1", "0x2"]), &hash(&["0x3", "0x4"])]);
+    let p2 = hash(&["0x5", "0x6"]);
+    let p3 = "0x7".to_string();
+
+    let expected = [
+        hash(&["11", &hash(&[&p1, &hash(&[&p2, &p3])])]),
+        hash(&["11", &hash(&[&hash(&[&p1, &p2]), &p3])]),
+        hash(&[&hash(&[&p3, &p2]), &p1]),
+        hash(&["11", &hash(&[&p1, &p2, &p3])]),
+    ];
+
+    for (peak_bagging, expected) in POLICIES.into_iter().zip(expected) {
+        let (_, _, root_hash) = setup(peak_bagging, 7).await;
+        assert_eq!(root_hash, expected, "{}", peak_bagging);
+    }
+
+    //? The default is the historical bagging of this crate
+    let (_, _, default_root) = setup(PeakBagging::default(), 7).await;
+    assert_eq!(
+        default_root,
+        "0x24c7375eb53c5f0e4c5d53c8e4873618d73ccc250edae7412e99df99ae3f7f18"
+    );
+}
+
+#[tokio::test]
+async fn should_match_ckb_merkle_mountain_range_roots() {
+    //? Roots of ckb-merkle-mountain-range 0.5.2 and polkadot-ckb-merkle-mountain-range 0.8.1,
+    //? the MMR of Polkadot's pallet, over the 32 bytes leaves 1..=n with `merge` as
+    //? `keccak256(left ++ right)`. Both crates return the same roots.
+    let expected = [
+        "0x0000000000000000000000000000000000000000000000000000000000000001",
+        "0xe90b7bceb6e7df5418fb78d8ee546e97c83a08bbccc01a0644d599ccd2a7c2e0",
+        "0x9b0225f2c6f59eeaf8302811ea290e95258763189b82dc033158e99a6ef45a87",
+        "0xa9bb8c3f1f12e9aa903a50c47f314b57610a3ab32f2d463293f58836def38d36",
+        "0x4e1cf95dbd822c8af1e14e5526b4d66f8bef8cbd8ad3e37743af3a7fc51ce7a8",
+        "0x1c753d5799e3bdb9e59b3b2577565f13d036c79b35787073eae5da32300a465c",
+        "0x2eb08759547a6ec244d672cb94646be4454da51fa698b517662138610935f805",
+        "0x6f4feb766c4e9e71bf038b8df02f0966e2bf98fe1eaacfd96e5d036664ca1b3c",
+        "0xf4af551ca50de4e354036343749a3e08411ae024edb1b728689d4a4530f3a615",
+        "0x5b2618581fd0543efdab8fd961af5f9094680437ea73cc6573a2feece3932b58",
+        "0x3187f5935e6c9a1a2b49f2acd007bb058198792665839bcd0d2cfdc9e32cb6d8",
+    ];
+
+    let (mut mmr, _, _) = setup(PeakBagging::RightToLeftWithoutSize, 0).await;
+    for (i, expected) in expected.into_iter().enumerate() {
+        let root_hash = mmr
+            .append(format!("0x{:x}", i + 1))
+            .await
+            .unwrap()
+            .root_hash;
+        //? A single leaf is its own root and keeps the unpadded form it was appended in
+        let root_hash = format!("0x{:0>64}", root_hash.trim_start_matches("0x"));
+        assert_eq!(root_hash, expected);
+    }
+}
+
+#[tokio::test]
+async fn should_bag_small_mmrs() {
+    let hasher = KeccakHasher::new();
+    for peak_bagging in POLICIES {
+        assert_eq!(peak_bagging.bag(&hasher, vec![]).unwrap(), "0x0");
+        assert_eq!(
+            peak_bagging.bag(&hasher, vec!["0x1".to_string()]).unwrap(),
+            "0x1"
+        );
+
+        let (_, _, root_hash) = setup(peak_bagging, 1).await;
+        let expected = match peak_bagging {
+            PeakBagging::RightToLeftWithoutSize => "0x1".to_string(),
+            _ => hasher
+                .hash(vec!["1".to_string(), "0x1".to_string()])
+                .unwrap(),
+        };
+        assert_eq!(root_hash, expected);
+    }
+}
+
+#[tokio::test]
+async fn should_keep_peak_bagging_across_views() {
+    for peak_bagging in POLICIES {
+        let (mut mmr, _, root_hash) = setup(peak_bagging, 7).await;
+
+        let opened = MMR::open(mmr.store.clone(), mmr.hasher.clone(), mmr.mmr_id.clone())
+            .await
+            .unwrap();
+        assert_eq!(opened.peak_bagging, peak_bagging);
+
+        let compact = CompactMMR::from_mmr(&mmr).await.unwrap();
+        assert_eq!(compact.root_hash().unwrap(), root_hash);
+
+        let mut snapshot = Vec::new();
+        let header = mmr.export_snapshot(&mut snapshot).await.unwrap();
+        assert_eq!(header.peak_bagging, peak_bagging);
+        let imported = MMR::import_snapshot(
+            snapshot.as_slice(),
+            Arc::new(InMemoryStore::default()),
+            mmr.hasher.clone(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(imported.peak_bagging, peak_bagging);
+
+        let mut draft = mmr.start_draft().await.unwrap();
+        let draft_root = draft.mmr.append("0x8".to_string()).await.unwrap().root_hash;
+        draft.commit().await.unwrap();
+        let mut compact = compact;
+        assert_eq!(
+            compact.append("0x8".to_string()).unwrap().root_hash,
+            draft_root
+        );
+        let bag = mmr.bag_the_peaks(None).await.unwrap();
+        assert_eq!(mmr.calculate_root_hash(&bag, 15).unwrap(), draft_root);
+    }
+}
+
+#[tokio::test]
+async fn should_verify_proofs_with_peak_bagging() {
+    for peak_bagging in POLICIES {
+        let (mmr, values, root_hash) = setup(peak_bagging, 7).await;
+        let hasher = mmr.hasher.clone();
+
+        let proof = mmr.get_leaf_range_proof(2, 5, None).await.unwrap();
+        assert!(proof
+            .verify(
+                hasher.as_ref(),
+                &values[2..=5],
+                &root_hash,
+                &mmr.hashing_options()
+            )
+            .unwrap());
+
+        let proof = mmr.get_range_proof("0x3", "0x5").await.unwrap();
+        assert!(proof
+            .verify(hasher.as_ref(), &root_hash, &mmr.hashing_options())
+            .unwrap());
+
+        //? The same proof checked with another bagging does not match the root
+        let other_bagging = HashingOptions {
+            peak_bagging: match peak_bagging {
+                PeakBagging::RightToLeft => PeakBagging::LeftToRight,
+                _ => PeakBagging::RightToLeft,
+            },
+            ..mmr.hashing_options()
+        };
+        assert!(!proof
+            .verify(hasher.as_ref(), &root_hash, &other_bagging)
+            .unwrap());
+    }
+}
+
+#[test]
+fn should_parse_peak_bagging() {
+    for peak_bagging in POLICIES {
+        assert_eq!(
+            PeakBagging::from_str(&peak_bagging.to_string()).unwrap(),
+            peak_bagging
+        );
+    }
+    assert!(matches!(
+        PeakBagging::from_str("middle_out"),
+        Err(MMRError::InvalidPeakBagging)
+    ));
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use accumulators::{
+    hasher::stark_poseidon::StarkPoseidonHasher,
+    mmr::{MMRError, PeaksOptions, MMR},
+    store::{memory::InMemoryStore, sqlite::SQLiteStore, SubKey},
+};
+
+#[tokio::test]
+async fn should_prove_pinned_leaves_after_pruning() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut mmr = MMR::new(store.clone(), hasher.clone(), None);
+    let mut appends = vec![];
+    for i in 1..=11 {
+        appends.push(mmr.append(i.to_string()).await.unwrap());
+    }
+    let peaks_before = mmr
+        .get_peaks(PeaksOptions {
+            elements_count: None,
+            formatting_opts: None,
+        })
+        .await
+        .unwrap();
+    let pinned = appends[5].element_index;
+
+    assert_eq!(pinned, 9);
+
+    //? 19 elements, the peaks are 15, 18 and 19 and the leaf 9 is proven by 8, 13 and 7
+    let retained = [7, 8, 9, 13, 15, 18, 19];
+    let deleted = mmr.prune(vec![pinned]).await.unwrap();
+    assert_eq!(deleted, 19 - retained.len());
+    for element_index in 1..=19 {
+        let hash = mmr.hashes.get(SubKey::Usize(element_index)).await.unwrap();
+        assert_eq!(
+            hash.is_some(),
+            retained.contains(&element_index),
+            "element {}",
+            element_index
+        );
+    }
+
+    //? Peaks are retained
+    let peaks_after = mmr
+        .get_peaks(PeaksOptions {
+            elements_count: None,
+            formatting_opts: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(peaks_before, peaks_after);
+
+    let proof = mmr.get_proof(pinned, None).await.unwrap();
+    assert!(mmr
+        .verify_proof(proof, "6".to_string(), None)
+        .await
+        .unwrap());
+
+    assert!(matches!(
+        mmr.get_proof(appends[0].element_index, None).await,
+        Err(MMRError::PrunedElement(1))
+    ));
+    assert!(matches!(
+        mmr.get_proofs(vec![pinned, appends[1].element_index], None)
+            .await,
+        Err(MMRError::PrunedElement(2))
+    ));
+}
+
+#[tokio::test]
+async fn should_keep_appending_and_proving_after_pruning() {
+    let store = Arc::new(
+        SQLiteStore::new(":memory:", None, Some("test"))
+            .await
+            .unwrap(),
+    );
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut mmr = MMR::new(store.clone(), hasher.clone(), None);
+    let mut ref_mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    let mut appends = vec![];
+    for i in 1..=7 {
+        appends.push(mmr.append(i.to_string()).await.unwrap());
+        ref_mmr.append(i.to_string()).await.unwrap();
+    }
+    let pinned = appends[2].element_index;
+    mmr.prune(vec![pinned]).await.unwrap();
+
+    for i in 8..=20 {
+        let append = mmr.append(i.to_string()).await.unwrap();
+        assert_eq!(append, ref_mmr.append(i.to_string()).await.unwrap());
+        appends.push(append);
+    }
+
+    let proof = mmr.get_proof(pinned, None).await.unwrap();
+    assert_eq!(proof, ref_mmr.get_proof(pinned, None).await.unwrap());
+    assert!(mmr
+        .verify_proof(proof, "3".to_string(), None)
+        .await
+        .unwrap());
+
+    let new_leaf = appends[15].element_index;
+    let proof = mmr.get_proof(new_leaf, None).await.unwrap();
+    assert!(mmr
+        .verify_proof(proof, "16".to_string(), None)
+        .await
+        .unwrap());
+
+    //? Pruning again can only keep leaves that are still provable
+    assert!(matches!(
+        mmr.prune(vec![appends[0].element_index]).await,
+        Err(MMRError::PrunedElement(1))
+    ));
+    mmr.prune(vec![new_leaf]).await.unwrap();
+    assert!(matches!(
+        mmr.get_proof(pinned, None).await,
+        Err(MMRError::PrunedElement(_))
+    ));
+    let proof = mmr.get_proof(new_leaf, None).await.unwrap();
+    assert!(mmr
+        .verify_proof(proof, "16".to_string(), None)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn should_reject_pinning_internal_nodes() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut mmr = MMR::new(store, hasher, None);
+    for i in 1..=4 {
+        mmr.append(i.to_string()).await.unwrap();
+    }
+
+    assert!(matches!(
+        mmr.prune(vec![3]).await,
+        Err(MMRError::InvalidElementIndex)
+    ));
+}
+
+#[tokio::test]
+async fn should_reject_pruning_stacked_mmrs() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut mmr = MMR::new(store.clone(), hasher.clone(), None);
+    for i in 1..=4 {
+        mmr.append(i.to_string()).await.unwrap();
+    }
+    let mut draft = mmr.start_draft().await.unwrap();
+    draft.mmr.append("5".to_string()).await.unwrap();
+
+    assert!(matches!(
+        draft.mmr.prune(vec![1]).await,
+        Err(MMRError::StackedPruning)
+    ));
+    assert!(mmr.hashes.get(SubKey::Usize(1)).await.unwrap().is_some());
+}
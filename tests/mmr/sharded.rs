@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use accumulators::{
+    hasher::{stark_poseidon::StarkPoseidonHasher, DomainSeparation, NodeHashing},
+    mmr::{
+        sharded::{InMemoryShardStores, SQLiteShardStores, ShardStoreFactory, ShardedMMR},
+        PeakBagging, MMR,
+    },
+    store::{memory::InMemoryStore, sqlite::SQLiteStore, Store, SubKey},
+};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn should_freeze_segments_and_match_regular_mmr() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let hot_store = Arc::new(InMemoryStore::default());
+    let shard_stores = Arc::new(InMemoryShardStores::default());
+
+    let mut sharded_mmr = ShardedMMR::open(
+        hot_store.clone(),
+        hasher.clone(),
+        None,
+        4,
+        shard_stores.clone(),
+    )
+    .await
+    .unwrap();
+    let mut ref_mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+
+    let mut appends = vec![];
+    for i in 1..=10 {
+        let sharded_append = sharded_mmr.append(i.to_string()).await.unwrap();
+        let ref_append = ref_mmr.append(i.to_string()).await.unwrap();
+        assert_eq!(sharded_append, ref_append);
+        appends.push(sharded_append);
+    }
+
+    //? A segment is frozen on the first append that brings it to at least 4 elements
+    assert_eq!(
+        sharded_mmr
+            .shards()
+            .iter()
+            .map(|(size, _)| *size)
+            .collect::<Vec<_>>(),
+        vec![4, 8, 15]
+    );
+
+    //? Frozen hashes are no longer in the hot store
+    assert_eq!(
+        hot_store
+            .get(&format!("{}:hashes:1", sharded_mmr.mmr_id))
+            .await
+            .unwrap(),
+        None
+    );
+    let shard_store = shard_stores
+        .shard_store(&ShardedMMR::shard_id(&sharded_mmr.mmr_id, 0))
+        .await
+        .unwrap();
+    assert!(shard_store
+        .get(&format!(
+            "{}:hashes:1",
+            ShardedMMR::shard_id(&sharded_mmr.mmr_id, 0)
+        ))
+        .await
+        .unwrap()
+        .is_some());
+
+    for (i, append) in appends.iter().enumerate() {
+        let proof = sharded_mmr
+            .get_proof(append.element_index, None)
+            .await
+            .unwrap();
+        let ref_proof = ref_mmr.get_proof(append.element_index, None).await.unwrap();
+        assert_eq!(proof, ref_proof);
+        assert!(sharded_mmr
+            .verify_proof(proof, (i + 1).to_string(), None)
+            .await
+            .unwrap());
+    }
+}
+
+#[tokio::test]
+async fn should_reopen_sharded_mmr_from_sqlite_files() {
+    let directory = std::env::temp_dir().join(format!("sharded-mmr-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let hot_path = directory.join("hot.db");
+    let hot_store = Arc::new(
+        SQLiteStore::new(&hot_path.to_string_lossy(), Some(true), Some("hot"))
+            .await
+            .unwrap(),
+    );
+
+    let mut sharded_mmr = ShardedMMR::open(
+        hot_store.clone(),
+        hasher.clone(),
+        Some("sharded".to_string()),
+        3,
+        Arc::new(SQLiteShardStores::new(&directory)),
+    )
+    .await
+    .unwrap();
+    for i in 1..=6 {
+        sharded_mmr.append(i.to_string()).await.unwrap();
+    }
+    let root_hash = sharded_mmr.root_hash.get(SubKey::None).await.unwrap();
+    let shards_count = sharded_mmr.shards().len();
+    drop(sharded_mmr);
+
+    let mut reopened = ShardedMMR::open(
+        hot_store,
+        hasher,
+        Some("sharded".to_string()),
+        3,
+        Arc::new(SQLiteShardStores::new(&directory)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(reopened.shards().len(), shards_count);
+    assert_eq!(
+        reopened.root_hash.get(SubKey::None).await.unwrap(),
+        root_hash
+    );
+
+    let proof = reopened.get_proof(1, None).await.unwrap();
+    assert!(reopened
+        .verify_proof(proof, "1".to_string(), None)
+        .await
+        .unwrap());
+
+    let append = reopened.append("7".to_string()).await.unwrap();
+    let proof = reopened
+        .get_proof(append.element_index, None)
+        .await
+        .unwrap();
+    assert!(reopened
+        .verify_proof(proof, "7".to_string(), None)
+        .await
+        .unwrap());
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[tokio::test]
+async fn should_reopen_with_recorded_hashing() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let hot_store = Arc::new(InMemoryStore::default());
+    let shard_stores = Arc::new(InMemoryShardStores::default());
+
+    let mut sharded_mmr = ShardedMMR::open(
+        hot_store.clone(),
+        hasher.clone(),
+        Some("sharded".to_string()),
+        3,
+        shard_stores.clone(),
+    )
+    .await
+    .unwrap()
+    .with_node_hashing(NodeHashing::Sorted)
+    .with_domain_separation(DomainSeparation::Tagged)
+    .with_peak_bagging(PeakBagging::RightToLeftWithoutSize);
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None)
+        .with_node_hashing(NodeHashing::Sorted)
+        .with_domain_separation(DomainSeparation::Tagged)
+        .with_peak_bagging(PeakBagging::RightToLeftWithoutSize);
+    for i in 1..=6 {
+        sharded_mmr.append(i.to_string()).await.unwrap();
+        mmr.append(i.to_string()).await.unwrap();
+    }
+    drop(sharded_mmr);
+
+    let mut reopened = ShardedMMR::open(
+        hot_store,
+        hasher,
+        Some("sharded".to_string()),
+        3,
+        shard_stores,
+    )
+    .await
+    .unwrap();
+    assert_eq!(reopened.hashing_options(), mmr.hashing_options());
+
+    //? Appends go on with the recorded settings instead of failing the metadata check
+    assert_eq!(
+        reopened.append("7".to_string()).await.unwrap().root_hash,
+        mmr.append("7".to_string()).await.unwrap().root_hash
+    );
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use accumulators::{
+    hasher::stark_poseidon::StarkPoseidonHasher,
+    mmr::{leaf_count_to_mmr_size, shared::SharedMMR, MMRError, ProofOptions, MMR},
+    store::{memory::InMemoryStore, sqlite::SQLiteStore, SubKey},
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn should_serialize_concurrent_appends() {
+    let store = Arc::new(
+        SQLiteStore::new(":memory:", None, Some("test"))
+            .await
+            .unwrap(),
+    );
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mmr = SharedMMR::new(MMR::new(store.clone(), hasher.clone(), None))
+        .await
+        .unwrap();
+
+    let mut tasks = vec![];
+    for task in 0..8 {
+        let mmr = mmr.clone();
+        tasks.push(tokio::spawn(async move {
+            for i in 0..10 {
+                mmr.append((task * 100 + i).to_string()).await.unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(mmr.elements_count(), leaf_count_to_mmr_size(80));
+
+    let reopened = MMR::new(store, hasher, Some(mmr.mmr_id().to_string()));
+    assert_eq!(reopened.leaves_count.get().await.unwrap(), 80);
+    assert_eq!(
+        reopened.root_hash.get(SubKey::None).await.unwrap().unwrap(),
+        mmr.get_root_hash().await.unwrap()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn should_prove_against_snapshot_while_appending() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mmr = SharedMMR::new(MMR::new(store.clone(), hasher.clone(), None))
+        .await
+        .unwrap();
+
+    let first = mmr.append("1".to_string()).await.unwrap();
+
+    let writer = {
+        let mmr = mmr.clone();
+        tokio::spawn(async move {
+            for i in 2..=200 {
+                mmr.append(i.to_string()).await.unwrap();
+            }
+        })
+    };
+
+    let mut readers = vec![];
+    for _ in 0..4 {
+        let mmr = mmr.clone();
+        readers.push(tokio::spawn(async move {
+            for _ in 0..50 {
+                let proof = mmr.get_proof(first.element_index, None).await.unwrap();
+                let options = ProofOptions {
+                    elements_count: Some(proof.elements_count),
+                    ..Default::default()
+                };
+                assert!(mmr
+                    .verify_proof(proof.clone(), "1".to_string(), Some(options))
+                    .await
+                    .unwrap());
+                assert!(proof.elements_count <= mmr.elements_count());
+            }
+        }));
+    }
+
+    writer.await.unwrap();
+    for reader in readers {
+        reader.await.unwrap();
+    }
+
+    let reopened = MMR::new(store, hasher, Some(mmr.mmr_id().to_string()));
+    assert_eq!(reopened.leaves_count.get().await.unwrap(), 200);
+    assert_eq!(
+        reopened.root_hash.get(SubKey::None).await.unwrap().unwrap(),
+        mmr.get_root_hash().await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn should_verify_against_the_snapshot_size() {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mmr = SharedMMR::new(MMR::new(store, hasher, None)).await.unwrap();
+    for i in 1..=3 {
+        mmr.append(i.to_string()).await.unwrap();
+    }
+
+    let proof = mmr.get_proof(1, None).await.unwrap();
+    assert!(mmr
+        .verify_proof(proof.clone(), "1".to_string(), None)
+        .await
+        .unwrap());
+
+    //? The proof cannot pick a size the snapshot has not reached
+    let mut forged = proof;
+    forged.elements_count = mmr.elements_count() + 1;
+    assert!(matches!(
+        mmr.verify_proof(forged, "1".to_string(), None).await,
+        Err(MMRError::InvalidElementCount)
+    ));
+}
@@ -0,0 +1,236 @@
+use std::{collections::HashMap, sync::Arc};
+
+use accumulators::{
+    hasher::{
+        keccak::KeccakHasher, stark_poseidon::StarkPoseidonHasher, DomainSeparation, NodeHashing,
+    },
+    mmr::{map_leaf_index_to_element_index, MMRError, MMR},
+    store::{memory::InMemoryStore, sqlite::SQLiteStore, Store, StoreError, SubKey},
+};
+use async_trait::async_trait;
+
+async fn setup(leaves: usize) -> MMR {
+    let store = Arc::new(InMemoryStore::default());
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+
+    let mut mmr = MMR::new(store, hasher, None);
+    for i in 1..=leaves {
+        mmr.append(i.to_string()).await.unwrap();
+    }
+    mmr
+}
+
+#[tokio::test]
+async fn should_export_and_import_snapshot() {
+    let mmr = setup(1500).await;
+    let mut snapshot = Vec::new();
+    let header = mmr.export_snapshot(&mut snapshot).await.unwrap();
+    assert_eq!(header.leaves_count, 1500);
+
+    let store = Arc::new(
+        SQLiteStore::new(":memory:", None, Some("test"))
+            .await
+            .unwrap(),
+    );
+    let imported = MMR::import_snapshot(
+        snapshot.as_slice(),
+        store,
+        Arc::new(StarkPoseidonHasher::new(Some(false))),
+        Some("imported".to_string()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(imported.mmr_id, "imported");
+    assert_eq!(
+        imported.elements_count.get().await.unwrap(),
+        header.elements_count
+    );
+    assert_eq!(imported.leaves_count.get().await.unwrap(), 1500);
+    assert_eq!(
+        imported.root_hash.get(SubKey::None).await.unwrap(),
+        Some(header.root_hash)
+    );
+
+    let element_index = map_leaf_index_to_element_index(1337);
+    let proof = imported.get_proof(element_index, None).await.unwrap();
+    assert_eq!(proof, mmr.get_proof(element_index, None).await.unwrap());
+}
+
+#[tokio::test]
+async fn should_roundtrip_snapshot_file() {
+    let mmr = setup(11).await;
+    let path = std::env::temp_dir().join(format!("snapshot-{}.jsonl", uuid::Uuid::new_v4()));
+    let header = mmr.export_snapshot_file(&path).await.unwrap();
+
+    let imported = MMR::import_snapshot_file(
+        &path,
+        Arc::new(InMemoryStore::default()),
+        Arc::new(StarkPoseidonHasher::new(Some(false))),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        imported.root_hash.get(SubKey::None).await.unwrap(),
+        Some(header.root_hash)
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn should_reject_tampered_snapshot() {
+    let mmr = setup(11).await;
+    let mut snapshot = Vec::new();
+    mmr.export_snapshot(&mut snapshot).await.unwrap();
+    let snapshot = String::from_utf8(snapshot).unwrap();
+
+    let mut lines: Vec<String> = snapshot.lines().map(str::to_string).collect();
+    //? Element 3 is the parent of the first two leaves
+    lines[3] = r#"{"index":3,"hash":"0x1"}"#.to_string();
+    let tampered_node = lines.join("\n");
+
+    let checksum_line = lines.len() - 1;
+    let mut lines: Vec<String> = snapshot.lines().map(str::to_string).collect();
+    lines[checksum_line] = r#"{"checksum":"0x0"}"#.to_string();
+    let tampered_checksum = lines.join("\n");
+
+    for tampered in [tampered_node, tampered_checksum] {
+        let store = Arc::new(InMemoryStore::default());
+        let result = MMR::import_snapshot(
+            tampered.as_bytes(),
+            store.clone(),
+            Arc::new(StarkPoseidonHasher::new(Some(false))),
+            None,
+        )
+        .await;
+
+        assert!(matches!(result, Err(MMRError::InvalidSnapshot(_))));
+        assert!(store.store.read().is_empty());
+    }
+}
+
+/// A store without `scan_prefix`
+#[derive(Debug, Default)]
+struct UnscannableStore(InMemoryStore);
+
+#[async_trait]
+impl Store for UnscannableStore {
+    fn id(&self) -> String {
+        self.0.id()
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, StoreError> {
+        self.0.get(key).await
+    }
+
+    async fn get_many(&self, keys: Vec<&str>) -> Result<HashMap<String, String>, StoreError> {
+        self.0.get_many(keys).await
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<(), StoreError> {
+        self.0.set(key, value).await
+    }
+
+    async fn set_many(&self, entries: HashMap<String, String>) -> Result<(), StoreError> {
+        self.0.set_many(entries).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StoreError> {
+        self.0.delete(key).await
+    }
+
+    async fn delete_many(&self, keys: Vec<&str>) -> Result<(), StoreError> {
+        self.0.delete_many(keys).await
+    }
+}
+
+#[tokio::test]
+async fn should_roll_back_import_without_scan_prefix() {
+    let mmr = setup(1500).await;
+    let mut snapshot = Vec::new();
+    mmr.export_snapshot(&mut snapshot).await.unwrap();
+    let snapshot = String::from_utf8(snapshot).unwrap();
+
+    //? The checksum is checked last, once every batch of hashes was written
+    let mut lines: Vec<String> = snapshot.lines().map(str::to_string).collect();
+    let checksum_line = lines.len() - 1;
+    lines[checksum_line] = r#"{"checksum":"0x0"}"#.to_string();
+    let tampered = lines.join("\n");
+
+    let store = Arc::new(UnscannableStore::default());
+    let result = MMR::import_snapshot(
+        tampered.as_bytes(),
+        store.clone(),
+        Arc::new(StarkPoseidonHasher::new(Some(false))),
+        None,
+    )
+    .await;
+
+    assert!(matches!(result, Err(MMRError::InvalidSnapshot(_))));
+    assert!(store.0.store.read().is_empty());
+}
+
+#[tokio::test]
+async fn should_reject_snapshot_of_other_hasher() {
+    let mmr = setup(3).await;
+    let mut snapshot = Vec::new();
+    mmr.export_snapshot(&mut snapshot).await.unwrap();
+
+    let result = MMR::import_snapshot(
+        snapshot.as_slice(),
+        Arc::new(InMemoryStore::default()),
+        Arc::new(KeccakHasher::new()),
+        None,
+    )
+    .await;
+    assert!(matches!(result, Err(MMRError::InvalidSnapshot(_))));
+}
+
+#[tokio::test]
+async fn should_not_import_into_non_empty_mmr() {
+    let mmr = setup(3).await;
+    let mut snapshot = Vec::new();
+    mmr.export_snapshot(&mut snapshot).await.unwrap();
+
+    let result = MMR::import_snapshot(
+        snapshot.as_slice(),
+        mmr.store.clone(),
+        mmr.hasher.clone(),
+        Some(mmr.mmr_id.clone()),
+    )
+    .await;
+    assert!(matches!(result, Err(MMRError::NonEmptyMMR)));
+    assert_eq!(mmr.leaves_count.get().await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn should_keep_hashing_of_snapshot() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None)
+        .with_node_hashing(NodeHashing::Sorted)
+        .with_domain_separation(DomainSeparation::Tagged);
+    for i in 1..=7 {
+        mmr.append(i.to_string()).await.unwrap();
+    }
+    let mut snapshot = Vec::new();
+    let header = mmr.export_snapshot(&mut snapshot).await.unwrap();
+    assert_eq!(header.node_hashing, NodeHashing::Sorted);
+    assert_eq!(header.domain_separation, DomainSeparation::Tagged);
+
+    let store = Arc::new(InMemoryStore::default());
+    let imported = MMR::import_snapshot(snapshot.as_slice(), store.clone(), hasher.clone(), None)
+        .await
+        .unwrap();
+    assert_eq!(imported.node_hashing, NodeHashing::Sorted);
+
+    let reopened = MMR::open(store, hasher, imported.mmr_id.clone())
+        .await
+        .unwrap();
+    assert_eq!(reopened.domain_separation, DomainSeparation::Tagged);
+    assert_eq!(
+        imported.root_hash.get(SubKey::None).await.unwrap(),
+        Some(header.root_hash)
+    );
+}
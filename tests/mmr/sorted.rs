@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use accumulators::{
+    hasher::{
+        keccak::KeccakHasher, stark_poseidon::StarkPoseidonHasher, DomainSeparation, Hasher,
+        HasherError, NodeHashing,
+    },
+    mmr::{HashingOptions, MMRError, PeakBagging, Proof, SortedRangeProof, MMR},
+    store::memory::InMemoryStore,
+};
+
+//? Keys 0xa, 0x14, ... 0x3e8, appended in increasing order
+async fn setup(hasher: Arc<dyn Hasher>) -> (MMR, String) {
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher, None);
+    let mut root_hash = String::new();
+    for key in (1..=100).map(|i| i * 10) {
+        root_hash = mmr.append(format!("0x{:x}", key)).await.unwrap().root_hash;
+    }
+    (mmr, root_hash)
+}
+
+#[tokio::test]
+async fn should_prove_absent_keys() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let (mmr, root_hash) = setup(hasher.clone()).await;
+
+    for (key, has_left, has_right) in [
+        ("0x0f", true, true),
+        ("0x1", false, true),
+        ("0x3e9", true, false),
+    ] {
+        let proof = mmr.get_absence_proof(key).await.unwrap();
+        assert_eq!(proof.left.is_some(), has_left);
+        assert_eq!(proof.right.is_some(), has_right);
+        assert!(proof
+            .verify_absence(hasher.as_ref(), &root_hash, key, &mmr.hashing_options())
+            .unwrap());
+        assert!(!proof
+            .verify_absence(hasher.as_ref(), &root_hash, "0x14", &mmr.hashing_options())
+            .unwrap());
+    }
+
+    let proof = mmr.get_absence_proof("0x0f").await.unwrap();
+    assert_eq!(proof.left.as_ref().unwrap().element_hash, "0xa");
+    assert_eq!(proof.right.as_ref().unwrap().element_hash, "0x14");
+
+    assert!(matches!(
+        mmr.get_absence_proof("0x14").await,
+        Err(MMRError::KeyPresent(_))
+    ));
+}
+
+#[tokio::test]
+async fn should_reject_non_adjacent_brackets() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let (mmr, root_hash) = setup(hasher.clone()).await;
+
+    //? Bracketing 0x15 with 0xa and 0x1e would hide the leaf 0x14
+    let mut proof = mmr.get_absence_proof("0x15").await.unwrap();
+    proof.left = mmr.get_absence_proof("0x0f").await.unwrap().left;
+    assert!(!proof
+        .verify_absence(hasher.as_ref(), &root_hash, "0x15", &mmr.hashing_options())
+        .unwrap());
+
+    //? The first leaf cannot be dropped to claim a key is below every leaf
+    let mut proof = mmr.get_absence_proof("0x0f").await.unwrap();
+    proof.left = None;
+    assert!(!proof
+        .verify_absence(hasher.as_ref(), &root_hash, "0x0f", &mmr.hashing_options())
+        .unwrap());
+
+    let proof = mmr.get_absence_proof("0x0f").await.unwrap();
+    assert!(!proof
+        .verify_absence(hasher.as_ref(), "0x1234", "0x0f", &mmr.hashing_options())
+        .unwrap());
+}
+
+#[tokio::test]
+async fn should_prove_ranges() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let (mmr, root_hash) = setup(hasher.clone()).await;
+
+    let proof = mmr.get_range_proof("0x10", "0x40").await.unwrap();
+    let keys: Vec<&str> = proof
+        .leaves
+        .iter()
+        .map(|leaf| leaf.element_hash.as_str())
+        .collect();
+    assert_eq!(keys, vec!["0x14", "0x1e", "0x28", "0x32", "0x3c"]);
+    assert!(proof
+        .verify(hasher.as_ref(), &root_hash, &mmr.hashing_options())
+        .unwrap());
+
+    let mut truncated = proof.clone();
+    truncated.leaves.pop();
+    assert!(!truncated
+        .verify(hasher.as_ref(), &root_hash, &mmr.hashing_options())
+        .unwrap());
+
+    let whole = mmr.get_range_proof("0x0", "0xffff").await.unwrap();
+    assert_eq!(whole.leaves.len(), 100);
+    assert!(whole.left.is_none() && whole.right.is_none());
+    assert!(whole
+        .verify(hasher.as_ref(), &root_hash, &mmr.hashing_options())
+        .unwrap());
+
+    assert!(matches!(
+        mmr.get_range_proof("0x40", "0x10").await,
+        Err(MMRError::InvalidRange)
+    ));
+
+    //? Keys are compared as hex numbers, anything else is rejected
+    assert!(matches!(
+        mmr.get_range_proof("0x10", "0x4g").await,
+        Err(MMRError::Hasher(HasherError::InvalidHash(_)))
+    ));
+    let mut forged = proof;
+    forged.end = "end".to_string();
+    assert!(matches!(
+        forged.verify(hasher.as_ref(), &root_hash, &mmr.hashing_options()),
+        Err(MMRError::Hasher(HasherError::InvalidHash(_)))
+    ));
+}
+
+#[tokio::test]
+async fn should_prove_absence_in_an_empty_mmr() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    let root_hash = mmr
+        .calculate_root_hash(&mmr.bag_the_peaks(None).await.unwrap(), 0)
+        .unwrap();
+
+    let proof = mmr.get_absence_proof("0x1").await.unwrap();
+    assert!(proof
+        .verify_absence(hasher.as_ref(), &root_hash, "0x1", &mmr.hashing_options())
+        .unwrap());
+}
+
+#[tokio::test]
+async fn should_refuse_tagged_leaves() {
+    let hasher = Arc::new(StarkPoseidonHasher::new(Some(false)));
+    let mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher, None)
+        .with_domain_separation(DomainSeparation::Tagged);
+
+    assert!(matches!(
+        mmr.get_absence_proof("0x1").await,
+        Err(MMRError::TaggedLeavesNotSearchable)
+    ));
+}
+
+#[tokio::test]
+async fn should_reject_proofs_with_forged_settings() {
+    let hasher = Arc::new(KeccakHasher::new());
+    let mut mmr = MMR::new(Arc::new(InMemoryStore::default()), hasher.clone(), None);
+    let mut root_hash = String::new();
+    for key in (1..=10).map(|i| i * 10) {
+        root_hash = mmr.append(format!("0x{:x}", key)).await.unwrap().root_hash;
+    }
+
+    //? A single peak that is the root itself brackets 0x14 away, were it bagged without the size
+    let forged = SortedRangeProof {
+        start: "0x14".to_string(),
+        end: "0x14".to_string(),
+        left: None,
+        leaves: vec![],
+        right: Some(Proof {
+            element_index: 1,
+            element_hash: root_hash.clone(),
+            siblings_hashes: vec![],
+            peaks_hashes: vec![root_hash.clone()],
+            elements_count: 1,
+        }),
+        peaks_hashes: vec![root_hash.clone()],
+        elements_count: 1,
+        node_hashing: mmr.node_hashing,
+    };
+    let without_size = HashingOptions {
+        peak_bagging: PeakBagging::RightToLeftWithoutSize,
+        ..mmr.hashing_options()
+    };
+    assert!(forged
+        .verify_absence(hasher.as_ref(), &root_hash, "0x14", &without_size)
+        .unwrap());
+    //? The trusted bagging commits to the size, so the forged peak no longer matches the root
+    assert!(!forged
+        .verify_absence(hasher.as_ref(), &root_hash, "0x14", &mmr.hashing_options())
+        .unwrap());
+
+    let forged = SortedRangeProof {
+        node_hashing: NodeHashing::Sorted,
+        ..forged
+    };
+    assert!(!forged
+        .verify_absence(hasher.as_ref(), &root_hash, "0x14", &mmr.hashing_options())
+        .unwrap());
+
+    let tagged = HashingOptions {
+        domain_separation: DomainSeparation::Tagged,
+        ..mmr.hashing_options()
+    };
+    assert!(matches!(
+        forged.verify_absence(hasher.as_ref(), &root_hash, "0x14", &tagged),
+        Err(MMRError::TaggedLeavesNotSearchable)
+    ));
+}
@@ -0,0 +1,64 @@
+// Exports conformance vectors from the TypeScript `accumulators` packages, one file per hasher.
+//
+//   npm install @accumulators/merkle-mountain-range @accumulators/memory @accumulators/hashers
+//   node tests/mmr/vectors/export/accumulators.mjs tests/mmr/vectors
+//
+// The files follow the format replayed by `tests/mmr/conformance.rs`. This script has not been
+// run yet, so no vector it exports is checked in.
+
+import { writeFileSync } from "node:fs";
+import { join } from "node:path";
+import { createRequire } from "node:module";
+import CoreMMR from "@accumulators/merkle-mountain-range";
+import MemoryStore from "@accumulators/memory";
+import { KeccakHasher, StarkPedersenHasher, StarkPoseidonHasher } from "@accumulators/hashers";
+
+const require = createRequire(import.meta.url);
+const { version } = require("@accumulators/merkle-mountain-range/package.json");
+const directory = process.argv[2] ?? ".";
+
+const hashers = [
+  ["keccak", new KeccakHasher()],
+  ["poseidon", new StarkPoseidonHasher()],
+  ["pedersen", new StarkPedersenHasher()],
+];
+
+for (const [name, hasher] of hashers) {
+  const mmr = new CoreMMR(new MemoryStore(), hasher);
+  const appends = [];
+  for (let leaf = 1; leaf <= 11; leaf++) {
+    const value = `0x${leaf.toString(16)}`;
+    const append = await mmr.append(value);
+    appends.push({
+      value,
+      element_index: append.elementIndex,
+      leaves_count: append.leavesCount,
+      elements_count: append.elementsCount,
+      root_hash: append.rootHash,
+      bag: await mmr.bagThePeaks(),
+    });
+  }
+
+  const proofs = [];
+  for (const { value, element_index } of appends) {
+    const proof = await mmr.getProof(element_index);
+    proofs.push({
+      element_index,
+      element_value: value,
+      siblings_hashes: proof.siblingsHashes,
+    });
+  }
+
+  const vector = {
+    name: `${name} appends and proofs`,
+    source: `@accumulators/merkle-mountain-range ${version}`,
+    hasher: name,
+    appends,
+    peaks: await mmr.getPeaks(),
+    proofs,
+  };
+  writeFileSync(
+    join(directory, `accumulators_${name}.json`),
+    `${JSON.stringify(vector, null, 2)}\n`,
+  );
+}
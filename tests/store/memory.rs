@@ -1,7 +1,9 @@
 use accumulators::store::{
     memory::InMemoryStore,
-    InStoreCounter, Store, {InStoreTable, SubKey},
+    InStoreCounter, Store, StoreError, {InStoreTable, SubKey},
 };
+use async_trait::async_trait;
+use futures::TryStreamExt;
 use std::{collections::HashMap, sync::Arc};
 
 #[tokio::test]
@@ -160,3 +162,106 @@ async fn test_batch_insertion_and_retrieval() {
         assert_eq!(values.get(&key), Some(&value));
     }
 }
+
+#[tokio::test]
+async fn should_compare_and_set() {
+    let store = InMemoryStore::default();
+
+    assert!(store.compare_and_set("key", None, "1").await.unwrap());
+    assert!(!store.compare_and_set("key", None, "2").await.unwrap());
+    assert!(!store.compare_and_set("key", Some("2"), "3").await.unwrap());
+    assert!(store.compare_and_set("key", Some("1"), "3").await.unwrap());
+    assert_eq!(store.get("key").await.unwrap(), Some("3".to_string()));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn should_increment_counter_concurrently() {
+    let store = Arc::new(InMemoryStore::default());
+    let counter = InStoreCounter::new(store.clone(), "counter".to_string());
+
+    let mut tasks = vec![];
+    for _ in 0..4 {
+        let counter = counter.clone();
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..100 {
+                counter.increment().await.unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(counter.get().await.unwrap(), 400);
+    assert_eq!(store.increment("counter", 5).await.unwrap(), 405);
+    assert!(counter.compare_and_set(405, 1).await.unwrap());
+    assert!(!counter.compare_and_set(405, 2).await.unwrap());
+}
+
+#[tokio::test]
+async fn should_scan_keys_by_prefix() {
+    let store = InMemoryStore::default();
+    let mut entries = HashMap::new();
+    entries.insert("a:2".to_string(), "value2".to_string());
+    entries.insert("a:1".to_string(), "value1".to_string());
+    entries.insert("ab:1".to_string(), "other".to_string());
+    entries.insert("b:1".to_string(), "other".to_string());
+    store.set_many(entries).await.unwrap();
+
+    let scanned: Vec<(String, String)> = store.scan_prefix("a:").try_collect().await.unwrap();
+    assert_eq!(
+        scanned,
+        vec![
+            ("a:1".to_string(), "value1".to_string()),
+            ("a:2".to_string(), "value2".to_string()),
+        ]
+    );
+
+    let scanned: Vec<(String, String)> = store.scan_prefix("c").try_collect().await.unwrap();
+    assert!(scanned.is_empty());
+}
+
+/// A store written against the trait before it had `scan_prefix`
+#[derive(Debug, Default)]
+struct LegacyStore(InMemoryStore);
+
+#[async_trait]
+impl Store for LegacyStore {
+    fn id(&self) -> String {
+        self.0.id()
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, StoreError> {
+        self.0.get(key).await
+    }
+
+    async fn get_many(&self, keys: Vec<&str>) -> Result<HashMap<String, String>, StoreError> {
+        self.0.get_many(keys).await
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<(), StoreError> {
+        self.0.set(key, value).await
+    }
+
+    async fn set_many(&self, entries: HashMap<String, String>) -> Result<(), StoreError> {
+        self.0.set_many(entries).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StoreError> {
+        self.0.delete(key).await
+    }
+
+    async fn delete_many(&self, keys: Vec<&str>) -> Result<(), StoreError> {
+        self.0.delete_many(keys).await
+    }
+}
+
+#[tokio::test]
+async fn should_not_scan_stores_without_scan_prefix() {
+    let store = LegacyStore::default();
+    store.set("a:1", "value1").await.unwrap();
+
+    let scanned: Result<Vec<(String, String)>, StoreError> =
+        store.scan_prefix("a:").try_collect().await;
+    assert!(matches!(scanned, Err(StoreError::ScanPrefixUnsupported)));
+}
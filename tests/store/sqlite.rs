@@ -2,6 +2,7 @@ use accumulators::store::{
     sqlite::SQLiteStore,
     InStoreCounter, Store, {InStoreTable, SubKey},
 };
+use futures::TryStreamExt;
 use std::{collections::HashMap, sync::Arc};
 
 #[tokio::test]
@@ -184,3 +185,79 @@ async fn test_batch_insertion_and_retrieval() {
         assert_eq!(values.get(&key), Some(&value));
     }
 }
+
+#[tokio::test]
+async fn should_compare_and_set() {
+    let store = SQLiteStore::new(":memory:", None, Some("test"))
+        .await
+        .unwrap();
+
+    assert!(store.compare_and_set("key", None, "1").await.unwrap());
+    assert!(!store.compare_and_set("key", None, "2").await.unwrap());
+    assert!(!store.compare_and_set("key", Some("2"), "3").await.unwrap());
+    assert!(store.compare_and_set("key", Some("1"), "3").await.unwrap());
+    assert_eq!(store.get("key").await.unwrap(), Some("3".to_string()));
+
+    assert_eq!(store.increment("other", 2).await.unwrap(), 2);
+    assert_eq!(store.increment("other", 3).await.unwrap(), 5);
+    assert_eq!(store.get("other").await.unwrap(), Some("5".to_string()));
+
+    let counter = InStoreCounter::new(Arc::new(store), "counter".to_string());
+    assert!(!counter.compare_and_set(1, 2).await.unwrap());
+    assert!(counter.compare_and_set(0, 2).await.unwrap());
+    assert_eq!(counter.increment().await.unwrap(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn should_increment_counter_from_several_connections() {
+    let path = std::env::temp_dir().join(format!("counter-{}.db", uuid::Uuid::new_v4()));
+    let path = path.to_string_lossy().to_string();
+
+    let mut tasks = vec![];
+    for _ in 0..4 {
+        let store = SQLiteStore::new(&path, Some(true), Some("test"))
+            .await
+            .unwrap();
+        let counter = InStoreCounter::new(Arc::new(store), "counter".to_string());
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..50 {
+                counter.increment().await.unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let store = SQLiteStore::new(&path, None, Some("test")).await.unwrap();
+    assert_eq!(store.get("counter").await.unwrap(), Some("200".to_string()));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn should_scan_keys_by_prefix_across_pages() {
+    let store = SQLiteStore::new(":memory:", None, Some("test"))
+        .await
+        .unwrap();
+    let entries: HashMap<String, String> = (0..2500)
+        .map(|i| (format!("a:{:04}", i), i.to_string()))
+        .chain([
+            ("ab:1".to_string(), "other".to_string()),
+            ("b:1".to_string(), "other".to_string()),
+            ("a%:1".to_string(), "other".to_string()),
+        ])
+        .collect();
+    store.set_many(entries).await.unwrap();
+
+    let scanned: Vec<(String, String)> = store.scan_prefix("a:").try_collect().await.unwrap();
+    assert_eq!(scanned.len(), 2500);
+    assert!(scanned
+        .iter()
+        .enumerate()
+        .all(|(i, (key, value))| *key == format!("a:{:04}", i) && *value == i.to_string()));
+
+    //? Wildcards are matched literally
+    let scanned: Vec<(String, String)> = store.scan_prefix("a%").try_collect().await.unwrap();
+    assert_eq!(scanned, vec![("a%:1".to_string(), "other".to_string())]);
+}